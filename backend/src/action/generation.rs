@@ -0,0 +1,192 @@
+use super::standard::*;
+use crate::board::*;
+use crate::rules::RuleSet;
+
+/// Offsets of the eight squares surrounding a location
+const ADJACENT: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+];
+
+/// Every action available to the pieces of the given color under the
+/// given rules, in FEN order of the pieces taking them
+pub fn generate_actions<const N: usize>(
+    board: &Grid<N>,
+    color: Color,
+    rules: &RuleSet,
+) -> Vec<StandardAction> {
+    let mut actions = Vec::new();
+    for (location, piece) in board.pieces() {
+        if piece.color == color {
+            actions.extend(generate_actions_at(board, location, rules));
+        }
+    }
+    actions
+}
+
+/// Every action available to the piece on the given location,
+/// regardless of whose turn it is
+pub fn generate_actions_at<const N: usize>(
+    board: &Grid<N>,
+    location: GridLocation,
+    rules: &RuleSet,
+) -> Vec<StandardAction> {
+    let piece = match board.get_unchecked(&location) {
+        Some(piece) => piece,
+        None => return Vec::new(),
+    };
+
+    let mut actions = Vec::new();
+    actions.extend(generate_rotations(location));
+    actions.extend(generate_moves(board, location));
+    actions.extend(generate_shoves(board, location, piece, rules));
+    actions.extend(generate_null_move(location, piece, rules));
+    actions
+}
+
+/// Locations next to the given one on a board with the given number of rows and columns
+fn adjacent_locations(location: GridLocation, size: usize) -> impl Iterator<Item = GridLocation> {
+    ADJACENT
+        .iter()
+        .filter_map(move |&(dx, dy)| location.offset(dx, dy, size))
+}
+
+fn generate_rotations(location: GridLocation) -> impl Iterator<Item = StandardAction> {
+    [Rotation::Right, Rotation::Left, Rotation::UTurn]
+        .into_iter()
+        .map(move |rotation| StandardAction::rotation(location, rotation))
+}
+
+fn generate_moves<const N: usize>(
+    board: &Grid<N>,
+    location: GridLocation,
+) -> impl Iterator<Item = StandardAction> + '_ {
+    adjacent_locations(location, N)
+        .filter(|target| board.get_unchecked(target).is_none())
+        .map(move |target| StandardAction::movement(location, target))
+}
+
+fn generate_shoves<'a, const N: usize>(
+    board: &'a Grid<N>,
+    location: GridLocation,
+    piece: StandardPiece,
+    rules: &'a RuleSet,
+) -> impl Iterator<Item = StandardAction> + 'a {
+    adjacent_locations(location, N)
+        .filter(move |target| match board.get_unchecked(target) {
+            Some(shoved) => rules.can_shove(piece, location, shoved, *target, N).is_ok(),
+            None => false,
+        })
+        .map(move |target| StandardAction::movement(location, target))
+}
+
+fn generate_null_move(
+    location: GridLocation,
+    piece: StandardPiece,
+    rules: &RuleSet,
+) -> Option<StandardAction> {
+    rules
+        .can_pass(piece)
+        .then(|| StandardAction::null(location))
+}
+
+#[cfg(test)]
+pub mod generation_tests {
+    use super::*;
+    use crate::action::Action;
+
+    fn count(board: &LeiserChessGrid, actions: &[StandardAction], kind: ActionKind) -> usize {
+        actions.iter().filter(|a| a.kind(board) == kind).count()
+    }
+
+    #[test]
+    pub fn opening_actions() {
+        let opening_position = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS";
+        let grid = LeiserChessGrid::from_str(opening_position).unwrap();
+        for color in [Color::White, Color::Black] {
+            let actions = generate_actions(&grid, color, &RuleSet::default());
+            assert_eq!(actions.len(), 66);
+            assert_eq!(count(&grid, &actions, ActionKind::Rotation), 24);
+            assert_eq!(count(&grid, &actions, ActionKind::Move), 34);
+            assert_eq!(count(&grid, &actions, ActionKind::Shove), 6);
+            assert_eq!(count(&grid, &actions, ActionKind::Null), 2);
+        }
+    }
+
+    #[test]
+    pub fn generated_actions_validate() {
+        let grid =
+            LeiserChessGrid::from_str("nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS").unwrap();
+        for (_, rules) in RuleSet::PRESETS {
+            for color in [Color::White, Color::Black] {
+                for action in generate_actions(&grid, color, &rules) {
+                    assert!(action.validate(&grid, &rules).is_ok(), "{:?}", action);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn pawns_only_shove_outwards() {
+        // The pawn on d4 is closer to the center than the pawn on c3
+        let grid = LeiserChessGrid::from_str("8/8/2NE5/3nw4/8/8/8/8").unwrap();
+        let rules = RuleSet::default();
+        let inner = generate_actions_at(&grid, GridLocation::from_str("d4").unwrap(), &rules);
+        let outer = generate_actions_at(&grid, GridLocation::from_str("c3").unwrap(), &rules);
+        assert_eq!(count(&grid, &inner, ActionKind::Shove), 1);
+        assert_eq!(count(&grid, &outer, ActionKind::Shove), 0);
+    }
+
+    #[test]
+    pub fn rules_change_available_actions() {
+        let grid = LeiserChessGrid::from_str("8/8/2NN5/3ss4/8/8/8/8").unwrap();
+        let monarch = GridLocation::from_str("c3").unwrap();
+        let strict = generate_actions_at(&grid, monarch, &RuleSet::LEISERCHESS_2023);
+        let relaxed = generate_actions_at(&grid, monarch, &RuleSet::RELAXED);
+        assert_eq!(count(&grid, &strict, ActionKind::Shove), 0);
+        assert_eq!(count(&grid, &relaxed, ActionKind::Shove), 1);
+
+        let no_passing = RuleSet {
+            null_moves: false,
+            ..RuleSet::default()
+        };
+        let actions = generate_actions_at(&grid, monarch, &no_passing);
+        assert_eq!(count(&grid, &actions, ActionKind::Null), 0);
+        assert!(StandardAction::null(monarch)
+            .validate(&grid, &no_passing)
+            .is_err());
+    }
+
+    #[test]
+    pub fn generation_is_symmetric() {
+        let grid =
+            LeiserChessGrid::from_str("nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS").unwrap();
+        let rules = RuleSet::default();
+        for symmetry in Symmetry::ALL {
+            let transformed = grid.transformed(symmetry);
+            for color in [Color::White, Color::Black] {
+                let mut expected: Vec<String> = generate_actions(&grid, color, &rules)
+                    .iter()
+                    .map(|action| action.transformed(symmetry, 8).to_string())
+                    .collect();
+                let color = match symmetry {
+                    Symmetry::SwapColors => color.opposite(),
+                    _ => color,
+                };
+                let mut actions: Vec<String> = generate_actions(&transformed, color, &rules)
+                    .iter()
+                    .map(|action| action.to_string())
+                    .collect();
+                expected.sort();
+                actions.sort();
+                assert_eq!(actions, expected);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::board::Indexable;
 use crate::board::OptimizedIndexable;
+use crate::rules::RuleSet;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,20 +8,28 @@ pub enum Error {
     #[error("Invalid action {0}")]
     InvalidAction(String),
 }
+pub mod generation;
 pub mod standard;
 
 pub trait Action<T: Indexable>: OptimizedAction<T> {
-    /// Perform simple logical validations on the current board
+    /// Perform simple logical validations on the current board under the given rules,
     /// returns error if able to tell that the action is invalid
-    fn validate(&self, board: &T) -> Result<(), Error>;
+    fn validate(&self, board: &T, rules: &RuleSet) -> Result<(), Error>;
 
-    fn apply(&self, board: &mut T) -> Result<(), Error> {
-        self.validate(board)?;
-        self.apply_unchecked(board);
-        Ok(())
+    fn apply(&self, board: &mut T, rules: &RuleSet) -> Result<Self::Undo, Error> {
+        self.validate(board, rules)?;
+        Ok(self.apply_unchecked(board))
     }
 }
 
 pub trait OptimizedAction<T: OptimizedIndexable> {
-    fn apply_unchecked(&self, board: &mut T);
+    /// Record of everything an action changed,
+    /// enough to restore the board to its previous state
+    type Undo;
+
+    fn apply_unchecked(&self, board: &mut T) -> Self::Undo;
+
+    /// Reverts an action previously applied to the board,
+    /// given the undo record that applying it returned
+    fn unapply_unchecked(&self, board: &mut T, undo: &Self::Undo);
 }
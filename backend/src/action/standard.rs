@@ -1,8 +1,60 @@
 use super::*;
 use crate::action::Error;
 use crate::board::*;
-use crate::constants::*;
+use crate::rules::RuleSet;
+use regex::Regex;
+use std::sync::LazyLock;
 
+/// A source square followed by a destination square or a rotation
+static ACTION_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([a-z][1-9][0-9]?)([a-z][1-9][0-9]?|[LRU])$").unwrap());
+
+/// The kinds of action a player can take on their turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ActionKind {
+    Move,
+    Shove,
+    Rotation,
+    Null,
+}
+
+/// What happened to a piece that was shoved
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShoveOutcome {
+    /// The piece was pushed onto the given empty location
+    Displaced(GridLocation),
+    /// The piece was pushed off the edge of the board and removed
+    OffBoard,
+    /// The square behind the piece was occupied, so the piece was removed
+    Blocked,
+}
+
+/// Record of a piece that was shoved by an action
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shove {
+    /// The shoved piece
+    pub piece: StandardPiece,
+    /// Location of the shoved piece before the action
+    pub from: GridLocation,
+    pub outcome: ShoveOutcome,
+}
+
+/// Everything needed to restore a board after a StandardAction is applied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StandardUndo {
+    /// The moved piece, with its direction before any rotation
+    pub piece: StandardPiece,
+    /// Pieces removed as victims of the action and where they stood
+    pub victims: Vec<(GridLocation, StandardPiece)>,
+    /// The piece that was shoved, if any
+    pub shove: Option<Shove>,
+}
+
+/// An action described only by the squares involved, so that it can be
+/// written as the source square followed by either the destination square
+/// (moves, shoves and null moves) or L, R or U (rotations), just like the
+/// frontend does
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct StandardAction {
     /// Locations of pieces that are to be removed from the board
     victims: Vec<GridLocation>,
@@ -10,10 +62,8 @@ pub struct StandardAction {
     source: GridLocation,
     /// The final locations of the pieces that are to be moved
     destination: GridLocation,
-    /// Piece to be transformed (with original rotation)
-    piece: StandardPiece,
-    /// Rotation of the piece
-    new_direction: Option<Direction>,
+    /// Rotation of the piece relative to its current direction
+    rotation: Option<Rotation>,
 }
 
 impl StandardAction {
@@ -21,96 +71,361 @@ impl StandardAction {
         victims: Vec<GridLocation>,
         source: GridLocation,
         destination: GridLocation,
-        piece: StandardPiece,
-        new_direction: Option<Direction>,
+        rotation: Option<Rotation>,
     ) -> Self {
         StandardAction {
             victims,
             source,
             destination,
+            rotation,
+        }
+    }
+
+    /// Move the piece on the source to the destination,
+    /// shoving the piece there if there is one
+    pub fn movement(source: GridLocation, destination: GridLocation) -> Self {
+        StandardAction::new(vec![], source, destination, None)
+    }
+
+    /// Rotate the piece on the source in place
+    pub fn rotation(source: GridLocation, rotation: Rotation) -> Self {
+        StandardAction::new(vec![], source, source, Some(rotation))
+    }
+
+    /// Leave the monarch on the source as it is
+    pub fn null(source: GridLocation) -> Self {
+        StandardAction::new(vec![], source, source, None)
+    }
+
+    pub fn victims(&self) -> &[GridLocation] {
+        &self.victims
+    }
+
+    pub fn source(&self) -> GridLocation {
+        self.source
+    }
+
+    pub fn destination(&self) -> GridLocation {
+        self.destination
+    }
+
+    pub fn relative_rotation(&self) -> Option<Rotation> {
+        self.rotation
+    }
+
+    /// Push the piece on the destination one square further along
+    /// the direction of the action, as long as that square is empty
+    /// and on the board. Otherwise the shoved piece is removed
+    fn shove_unchecked<const N: usize>(&self, board: &mut Grid<N>) -> Shove {
+        let piece = board
+            .get_unchecked(&self.destination)
+            .expect("No piece to shove at destination");
+        let dx = self.destination.x as i32 - self.source.x as i32;
+        let dy = self.destination.y as i32 - self.source.y as i32;
+
+        let outcome = match self.destination.offset(dx, dy, N) {
+            None => ShoveOutcome::OffBoard,
+            Some(behind) if board.get_unchecked(&behind).is_some() => ShoveOutcome::Blocked,
+            Some(behind) => {
+                board.set_unchecked(&behind, piece);
+                ShoveOutcome::Displaced(behind)
+            }
+        };
+        board.remove_unchecked(&self.destination);
+
+        Shove {
             piece,
-            new_direction,
+            from: self.destination,
+            outcome,
+        }
+    }
+
+    /// The matching action on a board transformed by the symmetry,
+    /// with the given number of rows and columns
+    pub fn transformed(&self, symmetry: Symmetry, size: usize) -> StandardAction {
+        StandardAction {
+            victims: self
+                .victims
+                .iter()
+                .map(|victim| victim.transformed(symmetry, size))
+                .collect(),
+            source: self.source.transformed(symmetry, size),
+            destination: self.destination.transformed(symmetry, size),
+            rotation: self.rotation.map(|rotation| rotation.transformed(symmetry)),
+        }
+    }
+
+    /// Classify the action according to the board it is about to be applied to
+    pub fn kind<const N: usize>(&self, board: &Grid<N>) -> ActionKind {
+        if self.rotation.is_some() {
+            ActionKind::Rotation
+        } else if self.source == self.destination {
+            ActionKind::Null
+        } else if board.get_unchecked(&self.destination).is_some() {
+            ActionKind::Shove
+        } else {
+            ActionKind::Move
         }
     }
 }
 
-impl Action<LeiserChessGrid> for StandardAction {
-    fn validate(&self, board: &LeiserChessGrid) -> Result<(), Error> {
-        if self.victims.len() > MAX_VICTIMS {
+impl<const N: usize> Action<Grid<N>> for StandardAction {
+    fn validate(&self, board: &Grid<N>, rules: &RuleSet) -> Result<(), Error> {
+        if self.victims.len() > rules.max_victims {
             return Err(Error::InvalidAction("Too many victims".to_string()));
         }
+        if self
+            .victims
+            .iter()
+            .any(|victim| board.validate_location(victim).is_err())
+        {
+            return Err(Error::InvalidAction(
+                "Victim location is off the board".to_string(),
+            ));
+        }
 
-    
-        let source_piece = board
+        let piece = board
             .get(&self.source)
-            .expect("Unable to get source location");
-        if source_piece.is_none() {
-            return Err(Error::InvalidAction("No piece at source location".to_string()));
-        }
-    
-        let square = board
-            .get(&self.destination)
-            .expect("Unable to get destination location");
-        if let Some(piece) = square {
-            if matches!(piece.kind, Kind::Monarch) {
-                return Err(Error::InvalidAction(
-                    "Monarchs cannot be shoved".to_string(),
-                ));
-            }
+            .map_err(|_| Error::InvalidAction("Source location is off the board".to_string()))?
+            .ok_or_else(|| Error::InvalidAction("No piece at source location".to_string()))?;
 
-            // TODO: make sure to check if rules specify
-            // whether pieces can be shoved if they are equal
-            if self.source.qi() <= self.destination.qi() {
-                return Err(Error::InvalidAction(
-                    "Must shove from higher qi square".to_string(),
-                ));
-            }
-        }
-        if let Some(_) = self.new_direction {
+        if self.rotation.is_some() {
             if self.source != self.destination {
                 return Err(Error::InvalidAction(
                     "Source location and destination location must be the same for a rotation"
                         .to_string(),
                 ));
             }
-        } else {
-            if !self.source.is_adjacent(&self.destination) {
+            return Ok(());
+        }
+
+        if self.source == self.destination {
+            if !rules.can_pass(piece) {
                 return Err(Error::InvalidAction(
-                    "Source location must be adjacent to destination location".to_string(),
+                    "Only monarchs can make a null move, when the rules allow it".to_string(),
                 ));
             }
+            return Ok(());
+        }
+
+        if !self.source.is_adjacent(&self.destination) {
+            return Err(Error::InvalidAction(
+                "Source location must be adjacent to destination location".to_string(),
+            ));
+        }
+
+        let square = board.get(&self.destination).map_err(|_| {
+            Error::InvalidAction("Destination location is off the board".to_string())
+        })?;
+        if let Some(target) = square {
+            rules
+                .can_shove(piece, self.source, target, self.destination, N)
+                .map_err(|e| Error::InvalidAction(e.to_string()))?;
         }
 
         Ok(())
     }
 }
 
-impl OptimizedAction<LeiserChessGrid> for StandardAction {
-    fn apply_unchecked(&self, board: &mut LeiserChessGrid) {
+impl<const N: usize> OptimizedAction<Grid<N>> for StandardAction {
+    type Undo = StandardUndo;
+
+    fn apply_unchecked(&self, board: &mut Grid<N>) -> StandardUndo {
+        let mut victims = Vec::with_capacity(self.victims.len());
         for victim in &self.victims {
-            board.remove_unchecked(victim);
+            if let Some(piece) = board.get_unchecked(victim) {
+                victims.push((*victim, piece));
+                board.remove_unchecked(victim);
+            }
+        }
+
+        let shove = match self.kind(board) {
+            ActionKind::Shove => Some(self.shove_unchecked(board)),
+            _ => None,
+        };
+
+        let piece = board
+            .get_unchecked(&self.source)
+            .expect("No piece to move at source");
+        let mut moved = piece;
+        if let Some(rotation) = self.rotation {
+            moved.direction = rotation.applied_to(piece.direction);
+        }
+        board.remove_unchecked(&self.source);
+        board.set_unchecked(&self.destination, moved);
+
+        StandardUndo {
+            piece,
+            victims,
+            shove,
+        }
+    }
+
+    fn unapply_unchecked(&self, board: &mut Grid<N>, undo: &StandardUndo) {
+        // Undo everything in the reverse order it was applied
+        board.remove_unchecked(&self.destination);
+        board.set_unchecked(&self.source, undo.piece);
+
+        if let Some(shove) = undo.shove {
+            if let ShoveOutcome::Displaced(location) = shove.outcome {
+                board.remove_unchecked(&location);
+            }
+            board.set_unchecked(&shove.from, shove.piece);
+        }
+
+        for (location, piece) in undo.victims.iter().rev() {
+            board.set_unchecked(location, *piece);
         }
-        let mut piece = self.piece.clone();
-        piece.direction = self.new_direction.unwrap_or(self.piece.direction);
-        board.set_unchecked(&self.destination, piece);
     }
 }
 
 impl Parseable for StandardAction {
-    fn from_str(fen: &str) -> Self {
-        // TODO: there's no way to guarantee uniqueness
-        // of a standard action with the current format,
-        // as the initial location would be needed
-        unimplemented!()
+    fn from_str(notation: &str) -> Result<Self, ParseError> {
+        let captures = ACTION_PATTERN.captures(notation).ok_or_else(|| {
+            ParseError::new(ParseErrorKind::InvalidAction(notation.to_string()), 0)
+        })?;
+        let source = GridLocation::from_str(&captures[1])?;
+        let target = &captures[2];
+        let offset = captures[1].len();
+        let action = match target {
+            "L" | "R" | "U" => StandardAction::rotation(
+                source,
+                Rotation::from_str(target).map_err(|e| e.offset(offset))?,
+            ),
+            _ => StandardAction::movement(
+                source,
+                GridLocation::from_str(target).map_err(|e| e.offset(offset))?,
+            ),
+        };
+        Ok(action)
     }
 
     fn to_string(&self) -> String {
-        let location = self.destination.to_string();
-        if let Some(direction) = self.new_direction {
-            let rotation = rotation_str(self.piece.direction, direction).expect("Invalid rotation");
-            format!("{}{}", location, rotation)
-        } else {
-            format!("_{}", location)
+        let source = self.source.to_string();
+        match self.rotation {
+            Some(rotation) => format!("{}{}", source, rotation.to_string()),
+            None => format!("{}{}", source, self.destination.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod standard_tests {
+    use super::*;
+    use crate::action::generation::generate_actions;
+    use crate::action::Action;
+
+    fn shove(board: &mut LeiserChessGrid, source: &str, destination: &str) -> Option<Shove> {
+        let source = GridLocation::from_str(source).unwrap();
+        let destination = GridLocation::from_str(destination).unwrap();
+        StandardAction::movement(source, destination)
+            .apply(board, &RuleSet::default())
+            .expect("Shove should be valid")
+            .shove
+    }
+
+    #[test]
+    pub fn moves_leave_source_empty() {
+        let mut grid = LeiserChessGrid::from_str("8/8/8/3NE4/8/8/8/8").unwrap();
+        let action = StandardAction::from_str("d4e5").unwrap();
+        assert_eq!(
+            action.apply(&mut grid, &RuleSet::default()).unwrap().shove,
+            None
+        );
+        assert_eq!(grid.to_string(), "8/8/8/8/4NE3/8/8/8");
+    }
+
+    #[test]
+    pub fn shove_displaces_piece() {
+        let mut grid = LeiserChessGrid::from_str("8/8/4sw3/3NE4/8/8/8/8").unwrap();
+        let result = shove(&mut grid, "d4", "e3").unwrap();
+        assert_eq!(result.from.to_string(), "e3");
+        assert_eq!(
+            result.outcome,
+            ShoveOutcome::Displaced(GridLocation::from_str("f2").unwrap())
+        );
+        assert_eq!(grid.to_string(), "8/5sw2/4NE3/8/8/8/8/8");
+    }
+
+    #[test]
+    pub fn shove_off_board_or_blocked_removes_piece() {
+        let mut grid = LeiserChessGrid::from_str("8/8/8/8/8/8/SS7/nw7").unwrap();
+        let result = shove(&mut grid, "a7", "a8").unwrap();
+        assert_eq!(result.outcome, ShoveOutcome::OffBoard);
+        assert_eq!(grid.to_string(), "8/8/8/8/8/8/8/SS7");
+
+        let mut grid = LeiserChessGrid::from_str("8/8/8/8/8/SS7/nw7/ne7").unwrap();
+        let result = shove(&mut grid, "a6", "a7").unwrap();
+        assert_eq!(result.outcome, ShoveOutcome::Blocked);
+        assert_eq!(grid.to_string(), "8/8/8/8/8/8/SS7/ne7");
+    }
+
+    #[test]
+    pub fn unapply_restores_board() {
+        let positions = [
+            "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS",
+            "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS",
+            "8/8/8/8/8/SS7/nw7/ne7",
+        ];
+        for position in positions {
+            let grid = LeiserChessGrid::from_str(position).unwrap();
+            for color in [Color::White, Color::Black] {
+                for action in generate_actions(&grid, color, &RuleSet::default()) {
+                    let mut board = grid.clone();
+                    let undo = action.apply(&mut board, &RuleSet::default()).unwrap();
+                    action.unapply_unchecked(&mut board, &undo);
+                    assert_eq!(board, grid, "{:?}", action);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn unapply_restores_victims() {
+        let grid = LeiserChessGrid::from_str("nn7/8/8/8/8/8/8/SS5se1").unwrap();
+        let source = GridLocation::from_str("a8").unwrap();
+        let victims = vec![
+            GridLocation::from_str("a1").unwrap(),
+            GridLocation::from_str("g8").unwrap(),
+        ];
+        let action = StandardAction::new(victims, source, source, Some(Rotation::UTurn));
+
+        let mut board = grid.clone();
+        let undo = action.apply(&mut board, &RuleSet::default()).unwrap();
+        assert_eq!(undo.victims.len(), 2);
+        assert_eq!(board.to_string(), "8/8/8/8/8/8/8/NN7");
+        action.unapply_unchecked(&mut board, &undo);
+        assert_eq!(board, grid);
+    }
+
+    #[test]
+    pub fn notation_round_trips() {
+        for notation in ["e3e4", "a8a7", "h1h1", "d4L", "d4R", "d4U"] {
+            assert_eq!(
+                StandardAction::from_str(notation).unwrap().to_string(),
+                notation
+            );
+        }
+
+        let grid =
+            LeiserChessGrid::from_str("nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS").unwrap();
+        assert_eq!(
+            StandardAction::from_str("a8a8").unwrap().kind(&grid),
+            ActionKind::Null
+        );
+        assert_eq!(
+            StandardAction::from_str("a8a7").unwrap().kind(&grid),
+            ActionKind::Shove
+        );
+        assert_eq!(
+            StandardAction::from_str("a7R").unwrap().kind(&grid),
+            ActionKind::Rotation
+        );
+        for action in generate_actions(&grid, Color::White, &RuleSet::default()) {
+            assert_eq!(
+                StandardAction::from_str(&action.to_string()).unwrap(),
+                action
+            );
         }
     }
 }
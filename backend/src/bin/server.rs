@@ -0,0 +1,105 @@
+//! Serves the JSON API of backend::server::GameServer over HTTP, plus
+//! GET /games/{id}/events which streams every update to a game as
+//! server sent events
+
+use backend::server::GameServer;
+use std::env;
+use std::io::{self, Write};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Away from the port the frontend dev server usually takes
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).expect("Header must be valid")
+}
+
+/// Let the frontend call the API from whatever address it is served on
+fn cors_headers() -> Vec<Header> {
+    vec![
+        header("Access-Control-Allow-Origin", "*"),
+        header("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS"),
+        header("Access-Control-Allow-Headers", "Content-Type"),
+    ]
+}
+
+/// Send every update to the client as a server sent event
+/// until either side goes away
+fn stream_events(request: Request, updates: Receiver<String>) -> io::Result<()> {
+    let mut writer = request.into_writer();
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/event-stream\r\n\
+         Cache-Control: no-cache\r\n\
+         Access-Control-Allow-Origin: *\r\n\r\n"
+    )?;
+    writer.flush()?;
+    for update in updates {
+        write!(writer, "data: {}\n\n", update)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn respond(server: &Mutex<GameServer>, mut request: Request) -> io::Result<()> {
+    let url = request.url().to_string();
+    let method = request.method().clone();
+
+    if method == Method::Options {
+        let mut response = Response::empty(204);
+        for header in cors_headers() {
+            response.add_header(header);
+        }
+        return request.respond(response);
+    }
+
+    let events = url
+        .strip_prefix("/games/")
+        .and_then(|rest| rest.strip_suffix("/events"))
+        .and_then(|id| id.parse().ok());
+    if let (Method::Get, Some(id)) = (&method, events) {
+        let updates = server.lock().expect("Server lock poisoned").subscribe(id);
+        let Some(updates) = updates else {
+            return request.respond(Response::empty(404));
+        };
+        return stream_events(request, updates);
+    }
+
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body)?;
+    let result = GameServer::handle_shared(server, method.as_str(), &url, &body);
+
+    let mut response =
+        Response::from_string(result.body.to_string()).with_status_code(result.status);
+    response.add_header(header("Content-Type", "application/json"));
+    for header in cors_headers() {
+        response.add_header(header);
+    }
+    request.respond(response)
+}
+
+fn main() {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let server = Server::http(&address).unwrap_or_else(|e| {
+        eprintln!("Could not listen on {}: {}", address, e);
+        std::process::exit(1)
+    });
+    eprintln!("Listening on http://{}", address);
+
+    let games = Arc::new(Mutex::new(GameServer::new()));
+    for request in server.incoming_requests() {
+        let games = games.clone();
+        // Event streams stay open, so every request gets its own thread
+        thread::spawn(move || {
+            if let Err(e) = respond(&games, request) {
+                eprintln!("Failed to respond: {}", e);
+            }
+        });
+    }
+}
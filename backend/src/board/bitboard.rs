@@ -0,0 +1,257 @@
+use super::*;
+use crate::constants::*;
+
+/// A board stored as a handful of u64 masks with one bit per square,
+/// where the bit for a location is y * BOARD_SIZE + x
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LeiserChessBitboard {
+    /// Squares holding white and black pieces respectively
+    colors: [u64; 2],
+    /// Squares holding monarchs and pawns respectively
+    kinds: [u64; 2],
+    /// Squares holding pieces turned clockwise the given number of times,
+    /// starting from North for monarchs and NorthEast for pawns
+    orientations: [u64; 4],
+}
+
+fn bit(location: &GridLocation) -> u64 {
+    1 << (location.y * BOARD_SIZE + location.x)
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+fn kind_index(kind: Kind) -> usize {
+    match kind {
+        Kind::Monarch => 0,
+        Kind::Pawn => 1,
+    }
+}
+
+impl LeiserChessBitboard {
+    pub fn empty() -> Self {
+        LeiserChessBitboard::default()
+    }
+
+    /// Every occupied square
+    pub fn occupied(&self) -> u64 {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// Squares holding pieces of the given color and kind
+    pub fn pieces_of(&self, color: Color, kind: Kind) -> u64 {
+        self.colors[color_index(color)] & self.kinds[kind_index(kind)]
+    }
+}
+
+impl Board for LeiserChessBitboard {
+    fn validate_board(&self) -> Result<(), Error> {
+        let occupied = self.occupied();
+        if self.colors[0] & self.colors[1] != 0 {
+            return Err(Error::InvalidBoard(
+                "Square occupied by both colors".to_string(),
+            ));
+        }
+        if self.kinds[0] & self.kinds[1] != 0 || self.kinds[0] | self.kinds[1] != occupied {
+            return Err(Error::InvalidBoard(
+                "Square with inconsistent piece kind".to_string(),
+            ));
+        }
+        let orientations = self.orientations.iter().fold(0, |all, o| all | o);
+        let overlapping =
+            (0..4).any(|i| (i + 1..4).any(|j| self.orientations[i] & self.orientations[j] != 0));
+        if overlapping || orientations != occupied {
+            return Err(Error::InvalidBoard(
+                "Square with inconsistent orientation".to_string(),
+            ));
+        }
+
+        let count = occupied.count_ones() as usize;
+        if count == 0 {
+            return Err(Error::InvalidBoard("No pieces on the board".to_string()));
+        }
+        if count > MAX_PIECES {
+            return Err(Error::InvalidBoard(
+                "Too many pieces on the board".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl HumanReadable for LeiserChessBitboard {}
+
+impl Indexable for LeiserChessBitboard {
+    fn validate_location(&self, location: &GridLocation) -> Result<(), Error> {
+        if location.x >= BOARD_SIZE || location.y >= BOARD_SIZE {
+            return Err(Error::InvalidLocation);
+        }
+        Ok(())
+    }
+
+    fn validate_piece(&self, piece: &StandardPiece) -> Result<(), Error> {
+        match (piece.kind, piece.direction) {
+            (Kind::Monarch, Direction::Orthogonal(_)) => Ok(()),
+            (Kind::Pawn, Direction::Diagonal(_)) => Ok(()),
+            _ => Err(Error::InvalidPiece),
+        }
+    }
+}
+
+impl OptimizedIndexable for LeiserChessBitboard {
+    type Piece = StandardPiece;
+    type Location = GridLocation;
+
+    fn get_unchecked(&self, location: &GridLocation) -> Option<StandardPiece> {
+        use self::Diagonal::*;
+        use self::Orthogonal::*;
+
+        let bit = bit(location);
+        if self.occupied() & bit == 0 {
+            return None;
+        }
+
+        let color = if self.colors[0] & bit != 0 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        let kind = if self.kinds[0] & bit != 0 {
+            Kind::Monarch
+        } else {
+            Kind::Pawn
+        };
+        let orientation = self
+            .orientations
+            .iter()
+            .position(|mask| mask & bit != 0)
+            .expect("Occupied square without an orientation");
+        let direction = match (kind, orientation) {
+            (Kind::Monarch, 0) => Direction::Orthogonal(North),
+            (Kind::Monarch, 1) => Direction::Orthogonal(East),
+            (Kind::Monarch, 2) => Direction::Orthogonal(South),
+            (Kind::Monarch, _) => Direction::Orthogonal(West),
+            (Kind::Pawn, 0) => Direction::Diagonal(NorthEast),
+            (Kind::Pawn, 1) => Direction::Diagonal(SouthEast),
+            (Kind::Pawn, 2) => Direction::Diagonal(SouthWest),
+            (Kind::Pawn, _) => Direction::Diagonal(NorthWest),
+        };
+
+        Some(StandardPiece {
+            color,
+            kind,
+            direction,
+        })
+    }
+
+    fn set_unchecked(&mut self, location: &GridLocation, piece: StandardPiece) {
+        self.remove_unchecked(location);
+        let bit = bit(location);
+        self.colors[color_index(piece.color)] |= bit;
+        self.kinds[kind_index(piece.kind)] |= bit;
+        self.orientations[piece.direction.quarter_turns()] |= bit;
+    }
+
+    fn remove_unchecked(&mut self, location: &GridLocation) {
+        let mask = !bit(location);
+        for board in self
+            .colors
+            .iter_mut()
+            .chain(self.kinds.iter_mut())
+            .chain(self.orientations.iter_mut())
+        {
+            *board &= mask;
+        }
+    }
+}
+
+impl From<&LeiserChessGrid> for LeiserChessBitboard {
+    fn from(grid: &LeiserChessGrid) -> Self {
+        let mut board = LeiserChessBitboard::empty();
+        for (location, piece) in grid.pieces() {
+            board.set_unchecked(&location, piece);
+        }
+        board
+    }
+}
+
+impl From<&LeiserChessBitboard> for LeiserChessGrid {
+    fn from(board: &LeiserChessBitboard) -> Self {
+        let mut grid = LeiserChessGrid::empty();
+        let mut occupied = board.occupied();
+        while occupied != 0 {
+            let index = occupied.trailing_zeros() as usize;
+            occupied &= occupied - 1;
+            let location = GridLocation {
+                x: index % BOARD_SIZE,
+                y: index / BOARD_SIZE,
+            };
+            if let Some(piece) = board.get_unchecked(&location) {
+                grid.set_unchecked(&location, piece);
+            }
+        }
+        grid
+    }
+}
+
+impl Parseable for LeiserChessBitboard {
+    fn from_str(fen: &str) -> Result<Self, ParseError> {
+        Ok(LeiserChessBitboard::from(&LeiserChessGrid::from_str(fen)?))
+    }
+
+    fn to_string(&self) -> String {
+        LeiserChessGrid::from(self).to_string()
+    }
+}
+
+#[cfg(test)]
+pub mod bitboard_tests {
+    use super::*;
+
+    #[test]
+    pub fn converts_losslessly() {
+        for fen in [
+            "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS",
+            "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS",
+            "ww7/8/8/8/8/8/8/6ee1",
+        ] {
+            let grid = LeiserChessGrid::from_str(fen).unwrap();
+            let board = LeiserChessBitboard::from(&grid);
+            assert!(board.validate_board().is_ok());
+            assert_eq!(LeiserChessGrid::from(&board), grid);
+            assert_eq!(board.to_string(), fen);
+        }
+    }
+
+    #[test]
+    pub fn indexing_matches_grid() {
+        let fen = "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS";
+        let mut grid = LeiserChessGrid::from_str(fen).unwrap();
+        let mut board = LeiserChessBitboard::from_str(fen).unwrap();
+
+        let location = GridLocation::from_str("d4").unwrap();
+        let piece = grid
+            .get_unchecked(&GridLocation::from_str("a1").unwrap())
+            .unwrap();
+        grid.set_unchecked(&location, piece);
+        board.set_unchecked(&location, piece);
+        grid.remove_unchecked(&GridLocation::from_str("h8").unwrap());
+        board.remove_unchecked(&GridLocation::from_str("h8").unwrap());
+
+        for y in 0..BOARD_SIZE {
+            for x in 0..BOARD_SIZE {
+                let location = GridLocation { x, y };
+                assert_eq!(
+                    board.get_unchecked(&location),
+                    grid.get_unchecked(&location)
+                );
+            }
+        }
+        assert_eq!(board.pieces_of(Color::Black, Kind::Monarch).count_ones(), 3);
+        assert_eq!(board.pieces_of(Color::White, Kind::Monarch).count_ones(), 1);
+    }
+}
@@ -1,8 +1,16 @@
 use super::*;
 use crate::constants::*;
 use crate::parser::*;
+use crate::zobrist;
 use regex::Regex;
+use std::hash::{Hash, Hasher};
+use std::sync::LazyLock;
 
+/// A column letter followed by a row number, such as e4
+static LOCATION_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([a-z])([1-9][0-9]?)$").unwrap());
+
+#[derive(Clone, Copy, Debug)]
 pub struct GridLocation {
     pub x: usize,
     pub y: usize,
@@ -17,11 +25,35 @@ impl GridLocation {
         (dx.abs() <= 1) && (dy.abs() <= 1) && (dx.abs() + dy.abs() > 0)
     }
 
-    /// Qi of a location according to LeiserChess rules
-    pub fn qi(&self) -> i32 {
+    /// Returns the location shifted by (dx, dy), or None if the result
+    /// falls off a board with the given number of rows and columns
+    pub fn offset(&self, dx: i32, dy: i32, size: usize) -> Option<GridLocation> {
+        let x = self.x as i32 + dx;
+        let y = self.y as i32 + dy;
+        let size = size as i32;
+        if x < 0 || y < 0 || x >= size || y >= size {
+            return None;
+        }
+        Some(GridLocation {
+            x: x as usize,
+            y: y as usize,
+        })
+    }
+
+    /// Returns the adjacent location in the given direction, or None if the
+    /// result falls off a board with the given number of rows and columns
+    pub fn step(&self, direction: Orthogonal, size: usize) -> Option<GridLocation> {
+        let (dx, dy) = direction.delta();
+        self.offset(dx, dy, size)
+    }
+
+    /// Qi of a location according to LeiserChess rules, on a board
+    /// with the given number of rows and columns
+    pub fn qi(&self, size: usize) -> i32 {
         let x = (self.x * 2) as i32;
         let y = (self.y * 2) as i32;
-        (x - 7) * (x - 7) + (y - 7) * (y - 7)
+        let center = size as i32 - 1;
+        (x - center) * (x - center) + (y - center) * (y - center)
     }
 }
 
@@ -31,36 +63,86 @@ impl PartialEq for GridLocation {
     }
 }
 
+impl Eq for GridLocation {}
+
+impl Hash for GridLocation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.x.hash(state);
+        self.y.hash(state);
+    }
+}
+
 impl Parseable for GridLocation {
-    fn from_str(fen: &str) -> Self {
-        let re = Regex::new(r"([a-h])([1-8])").unwrap();
-        let captures = re.captures(fen).unwrap();
-        let x = captures.get(1).unwrap().as_str().chars().next().unwrap() as usize - 'a' as usize;
-        let y = captures.get(2).unwrap().as_str().parse::<usize>().unwrap() - 1;
-        GridLocation { x, y }
+    fn from_str(fen: &str) -> Result<Self, ParseError> {
+        let invalid = || ParseError::new(ParseErrorKind::InvalidLocation(fen.to_string()), 0);
+        let captures = LOCATION_PATTERN.captures(fen).ok_or_else(invalid)?;
+        let x = captures[1].chars().next().unwrap() as usize - 'a' as usize;
+        let y = captures[2].parse::<usize>().unwrap() - 1;
+        if x >= MAX_BOARD_SIZE || y >= MAX_BOARD_SIZE {
+            return Err(invalid());
+        }
+        Ok(GridLocation { x, y })
     }
 
     fn to_string(&self) -> String {
-        let x = (self.x as u8 + 'a' as u8) as char;
+        let x = (self.x as u8 + b'a') as char;
         let y = (self.y + 1).to_string();
         format!("{}{}", x, y)
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct LeiserChessGrid {
-    squares: [[Option<StandardPiece>; 8]; 8],
+/// A square board with N rows and N columns, from 2x2 up to
+/// MAX_BOARD_SIZE, so that smaller boards can be solved exhaustively
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Grid<const N: usize> {
+    squares: [[Option<StandardPiece>; N]; N],
+    /// Zobrist hash of the pieces on the board, kept up to date
+    /// whenever a square changes
+    zobrist: u64,
+}
+
+/// The standard 8x8 board
+pub type LeiserChessGrid = Grid<BOARD_SIZE>;
+
+impl<const N: usize> Grid<N> {
+    /// A grid with no pieces on it
+    pub fn empty() -> Self {
+        const { assert!(N >= 2 && N <= MAX_BOARD_SIZE, "Unsupported board size") };
+        Grid {
+            squares: [[None; N]; N],
+            zobrist: 0,
+        }
+    }
+
+    /// Zobrist hash of the pieces on the board, ignoring whose turn it is
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    /// Iterates over every occupied square in FEN order,
+    /// that is row by row starting from the first row
+    pub fn pieces(&self) -> impl Iterator<Item = (GridLocation, StandardPiece)> + '_ {
+        self.squares.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter_map(move |(x, square)| square.map(|piece| (GridLocation { x, y }, piece)))
+        })
+    }
+}
+
+impl<const N: usize> Default for Grid<N> {
+    fn default() -> Self {
+        Grid::empty()
+    }
 }
 
-impl Board for LeiserChessGrid {
+impl<const N: usize> Board for Grid<N> {
     fn validate_board(&self) -> Result<(), Error> {
         let mut count = 0;
         for row in self.squares.iter() {
-            for square in row.iter() {
-                if let Some(piece) = square {
-                    self.validate_piece(piece)?;
-                    count += 1;
-                }
+            for piece in row.iter().flatten() {
+                self.validate_piece(piece)?;
+                count += 1;
             }
         }
         if count == 0 {
@@ -74,11 +156,11 @@ impl Board for LeiserChessGrid {
         Ok(())
     }
 }
-impl HumanReadable for LeiserChessGrid {}
+impl<const N: usize> HumanReadable for Grid<N> {}
 
-impl Indexable for LeiserChessGrid {
+impl<const N: usize> Indexable for Grid<N> {
     fn validate_location(&self, location: &GridLocation) -> Result<(), Error> {
-        if location.x > 7 || location.y > 7 {
+        if location.x >= N || location.y >= N {
             return Err(Error::InvalidLocation);
         }
         Ok(())
@@ -93,7 +175,7 @@ impl Indexable for LeiserChessGrid {
     }
 }
 
-impl OptimizedIndexable for LeiserChessGrid {
+impl<const N: usize> OptimizedIndexable for Grid<N> {
     type Piece = StandardPiece;
     type Location = GridLocation;
 
@@ -103,59 +185,110 @@ impl OptimizedIndexable for LeiserChessGrid {
     }
 
     fn set_unchecked(&mut self, location: &GridLocation, piece: StandardPiece) {
+        self.remove_unchecked(location);
         let GridLocation { x, y } = *location;
         self.squares[y][x] = Some(piece);
+        self.zobrist ^= zobrist::piece_key(&piece, location);
     }
 
     fn remove_unchecked(&mut self, location: &GridLocation) {
         let GridLocation { x, y } = *location;
-        self.squares[y][x] = None;
+        if let Some(piece) = self.squares[y][x].take() {
+            self.zobrist ^= zobrist::piece_key(&piece, location);
+        }
     }
 }
 
-impl Parseable for LeiserChessGrid {
-    fn from_str(fen: &str) -> Self {
-        let mut grid = LeiserChessGrid {
-            squares: [[None; 8]; 8],
-        };
-        let mut fen = fen;
+impl<const N: usize> Parseable for Grid<N> {
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let mut grid = Grid::empty();
+        let mut fen = input;
         let mut x = 0;
         let mut y = 0;
-        while fen.len() > 0 {
-            let (piece, rest) = parse_piece_fen(&fen);
+        let row_length = |x: usize, y: usize, position: usize| {
+            ParseError::new(
+                ParseErrorKind::RowLength {
+                    row: y + 1,
+                    length: x,
+                    expected: N,
+                },
+                position,
+            )
+        };
+
+        while !fen.is_empty() {
+            let position = input.len() - fen.len();
+            let (piece, rest) = parse_piece_fen(fen);
             if let Some(piece) = piece {
-                grid.squares[y][x] = Some(StandardPiece::from_str(piece));
+                if x >= N {
+                    return Err(row_length(x + 1, y, position));
+                }
+                let piece = StandardPiece::from_str(piece).map_err(|e| e.offset(position))?;
+                grid.set_unchecked(&GridLocation { x, y }, piece);
                 x += 1;
                 fen = rest;
                 continue;
             }
-            let (number, rest) = parse_number(&fen);
+            let (number, rest) = parse_number(fen);
             if let Some(number) = number {
+                if number > N - x {
+                    return Err(row_length(x.saturating_add(number), y, position));
+                }
                 x += number;
                 fen = rest;
                 continue;
             }
-            let (row, rest) = parse_row(&fen);
-            if let Some(_) = row {
+            let (row, rest) = parse_row(fen);
+            if row.is_some() {
+                if x != N {
+                    return Err(row_length(x, y, position));
+                }
+                if y + 1 >= N {
+                    return Err(ParseError::new(
+                        ParseErrorKind::RowCount {
+                            count: y + 2,
+                            expected: N,
+                        },
+                        position,
+                    ));
+                }
                 y += 1;
                 x = 0;
                 fen = rest;
                 continue;
             }
-            let (whitespace, rest) = parse_whitespace(&fen);
-            if let Some(_) = whitespace {
+            let (whitespace, rest) = parse_whitespace(fen);
+            if whitespace.is_some() {
                 fen = rest;
                 continue;
             }
-            let (current_player, rest) = parse_current_player(&fen);
-            if let Some(_) = current_player {
-                // TODO: this is a suprise tool that can help us later...
+            let (current_player, rest) = parse_current_player(fen);
+            if current_player.is_some() {
+                // The side to move is kept by Position, not the grid
                 fen = rest;
                 continue;
             }
+            let unexpected = fen.chars().next().unwrap();
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedCharacter(unexpected),
+                position,
+            ));
         }
 
-        grid
+        if y + 1 != N {
+            return Err(ParseError::new(
+                ParseErrorKind::RowCount {
+                    count: y + 1,
+                    expected: N,
+                },
+                input.len(),
+            ));
+        }
+        if x != N {
+            return Err(row_length(x, y, input.len()));
+        }
+
+        Ok(grid)
     }
 
     fn to_string(&self) -> String {
@@ -190,7 +323,7 @@ pub mod grid_tests {
     #[test]
     pub fn fen_parsing() {
         let opening_position = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS";
-        let grid = LeiserChessGrid::from_str(opening_position);
+        let grid = LeiserChessGrid::from_str(opening_position).unwrap();
         println!("{}", grid.human_readable());
         assert_eq!(grid.to_string(), opening_position);
     }
@@ -198,16 +331,18 @@ pub mod grid_tests {
     #[test]
     pub fn parses_current_player() {
         let opening_position = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS B";
-        let gridw = LeiserChessGrid::from_str(opening_position);
+        let gridw = LeiserChessGrid::from_str(opening_position).unwrap();
         let opening_position = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS W";
-        let gridb = LeiserChessGrid::from_str(opening_position);
+        let gridb = LeiserChessGrid::from_str(opening_position).unwrap();
         assert_eq!(gridw.to_string(), gridb.to_string());
     }
 
     #[test]
+    #[allow(clippy::redundant_pattern_matching)]
     pub fn piece_validation() {
         let grid = LeiserChessGrid {
             squares: [[None; 8]; 8],
+            zobrist: 0,
         };
         assert!(matches!(
             grid.validate_piece(&StandardPiece {
@@ -230,9 +365,84 @@ pub mod grid_tests {
 
     #[test]
     pub fn grid_location_parsing() {
-        let location = GridLocation::from_str("a1");
+        let location = GridLocation::from_str("a1").unwrap();
         assert_eq!(location.to_string(), "a1");
-        let location = GridLocation::from_str("h8");
+        let location = GridLocation::from_str("h8").unwrap();
         assert_eq!(location.to_string(), "h8");
+        let location = GridLocation::from_str("j10").unwrap();
+        assert_eq!((location.x, location.y), (9, 9));
+        assert_eq!(location.to_string(), "j10");
+    }
+
+    #[test]
+    pub fn fen_errors_report_position() {
+        let error = LeiserChessGrid::from_str("nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SX")
+            .unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedCharacter('S'));
+        assert_eq!(error.position, 47);
+
+        let error = LeiserChessGrid::from_str("nn7nn/8/8/8/8/8/8/8").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::RowLength { row: 1, .. }
+        ));
+        assert_eq!(error.position, 3);
+
+        // Runs of empty squares too long to add up are still reported
+        let error = LeiserChessGrid::from_str("NN18446744073709551615").unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::RowLength {
+                row: 1,
+                length: usize::MAX,
+                expected: BOARD_SIZE,
+            }
+        );
+        assert_eq!(error.position, 2);
+
+        // Characters spanning more than one byte are not pieces
+        let error = LeiserChessGrid::from_str("Né").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnexpectedCharacter('N'));
+        assert_eq!(error.position, 0);
+
+        let error = LeiserChessGrid::from_str("8/8/8").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::RowCount { count: 3, .. }
+        ));
+
+        assert!(GridLocation::from_str("q1").is_err());
+        assert!(GridLocation::from_str("a17").is_err());
+        assert!(GridLocation::from_str("a0").is_err());
+        let outside = GridLocation::from_str("i9").unwrap();
+        assert!(LeiserChessGrid::empty().get(&outside).is_err());
+    }
+
+    #[test]
+    pub fn other_board_sizes() {
+        let small = "nn3/1se2/2NW1/3SS";
+        let grid = Grid::<4>::from_str(small).unwrap();
+        assert_eq!(grid.to_string(), small);
+        assert_eq!(
+            grid.human_readable(),
+            "nn . . .\n .se . .\n . .NW .\n . . .SS"
+        );
+        let error = Grid::<4>::from_str("nn6nn/8/8/8/8/8/8/8").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            ParseErrorKind::RowLength { expected: 4, .. }
+        ));
+
+        let large = "nn8nn/10/10/10/4SE5/10/10/10/10/SS9";
+        let grid = Grid::<10>::from_str(large).unwrap();
+        assert_eq!(grid.to_string(), large);
+        let corner = GridLocation::from_str("j10").unwrap();
+        assert!(grid.get(&corner).unwrap().is_none());
+        assert!(LeiserChessGrid::empty().get(&corner).is_err());
+
+        // Every square of the center is the same distance from it
+        let center = ["b2", "b3", "c2", "c3"].map(|s| GridLocation::from_str(s).unwrap().qi(4));
+        assert!(center.iter().all(|&qi| qi == 2));
+        assert_eq!(GridLocation::from_str("a1").unwrap().qi(4), 18);
     }
 }
@@ -1,10 +1,17 @@
+pub mod bitboard;
 pub mod grid;
 pub mod piece;
+pub mod position;
+pub mod symmetry;
 
+pub use bitboard::*;
 pub use grid::*;
 pub use piece::*;
+pub use position::*;
+pub use symmetry::*;
 
 pub use crate::action::Action;
+pub use crate::parser::{ParseError, ParseErrorKind};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -27,9 +34,10 @@ pub trait Board: Clone {
     fn validate_board(&self) -> Result<(), Error>;
 }
 
-pub trait Parseable {
-    /// Given a string, create a unique representation of the object
-    fn from_str(fen: &str) -> Self;
+pub trait Parseable: Sized {
+    /// Given a string, create a unique representation of the object,
+    /// or describe where the string stopped making sense
+    fn from_str(fen: &str) -> Result<Self, ParseError>;
 
     /// Convert the current representation to a string
     fn to_string(&self) -> String;
@@ -44,18 +52,23 @@ pub trait HumanReadable: Parseable + Board {
             panic!("Invalid board");
         }
         let fen = self.to_string();
+        let mut rest = fen.as_str();
         let mut result = String::new();
-        for c in fen.chars() {
-            if c == '/' {
-                result.push('\n');
-            } else if c.is_numeric() {
-                let n = c.to_digit(10).unwrap();
+        while let Some(c) = rest.chars().next() {
+            // Runs of empty squares may take more than one digit
+            if let (Some(n), after) = crate::parser::parse_number(rest) {
                 for _ in 0..n {
                     result.push_str(" .");
                 }
+                rest = after;
+                continue;
+            }
+            if c == '/' {
+                result.push('\n');
             } else {
                 result.push(c);
             }
+            rest = &rest[c.len_utf8()..];
         }
         result
     }
@@ -6,26 +6,55 @@ pub enum Error {
     InvalidRotation(Direction, Direction),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Color {
     White,
     Black,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl Parseable for Color {
+    fn from_str(notation: &str) -> Result<Color, ParseError> {
+        match notation {
+            "W" | "w" => Ok(Color::White),
+            "B" | "b" => Ok(Color::Black),
+            _ => Err(ParseError::new(
+                ParseErrorKind::InvalidPlayer(notation.to_string()),
+                0,
+            )),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        match self {
+            Color::White => "W".to_string(),
+            Color::Black => "B".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Kind {
     Pawn,
     Monarch,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Orthogonal {
     North,
     South,
     East,
     West,
 }
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Diagonal {
     NorthEast,
     NorthWest,
@@ -33,12 +62,89 @@ pub enum Diagonal {
     SouthWest,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Direction {
     Diagonal(Diagonal),
     Orthogonal(Orthogonal),
 }
 
+impl Direction {
+    /// Rotate right by 90 degrees, keeping the kind of direction
+    pub fn rotated_clockwise(self) -> Direction {
+        use self::Diagonal::*;
+        use self::Orthogonal::*;
+
+        match self {
+            Direction::Orthogonal(North) => Direction::Orthogonal(East),
+            Direction::Orthogonal(East) => Direction::Orthogonal(South),
+            Direction::Orthogonal(South) => Direction::Orthogonal(West),
+            Direction::Orthogonal(West) => Direction::Orthogonal(North),
+            Direction::Diagonal(NorthEast) => Direction::Diagonal(SouthEast),
+            Direction::Diagonal(SouthEast) => Direction::Diagonal(SouthWest),
+            Direction::Diagonal(SouthWest) => Direction::Diagonal(NorthWest),
+            Direction::Diagonal(NorthWest) => Direction::Diagonal(NorthEast),
+        }
+    }
+
+    /// Rotate left by 90 degrees, keeping the kind of direction
+    pub fn rotated_counter_clockwise(self) -> Direction {
+        self.rotated_180().rotated_clockwise()
+    }
+
+    pub fn rotated_180(self) -> Direction {
+        self.rotated_clockwise().rotated_clockwise()
+    }
+
+    /// Number of clockwise quarter turns from North,
+    /// or from NorthEast for diagonal directions
+    pub fn quarter_turns(self) -> usize {
+        use self::Diagonal::*;
+        use self::Orthogonal::*;
+
+        match self {
+            Direction::Orthogonal(North) | Direction::Diagonal(NorthEast) => 0,
+            Direction::Orthogonal(East) | Direction::Diagonal(SouthEast) => 1,
+            Direction::Orthogonal(South) | Direction::Diagonal(SouthWest) => 2,
+            Direction::Orthogonal(West) | Direction::Diagonal(NorthWest) => 3,
+        }
+    }
+}
+
+impl Orthogonal {
+    /// Change in (x, y) when travelling one square in this direction,
+    /// north is towards the first row of a FEN string
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Orthogonal::North => (0, -1),
+            Orthogonal::South => (0, 1),
+            Orthogonal::East => (1, 0),
+            Orthogonal::West => (-1, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Orthogonal {
+        match self {
+            Orthogonal::North => Orthogonal::South,
+            Orthogonal::South => Orthogonal::North,
+            Orthogonal::East => Orthogonal::West,
+            Orthogonal::West => Orthogonal::East,
+        }
+    }
+}
+
+impl Diagonal {
+    /// The two orthogonal directions that make up this diagonal,
+    /// which are also the faces of a pawn's mirror
+    pub fn decompose(self) -> (Orthogonal, Orthogonal) {
+        match self {
+            Diagonal::NorthEast => (Orthogonal::North, Orthogonal::East),
+            Diagonal::NorthWest => (Orthogonal::North, Orthogonal::West),
+            Diagonal::SouthEast => (Orthogonal::South, Orthogonal::East),
+            Diagonal::SouthWest => (Orthogonal::South, Orthogonal::West),
+        }
+    }
+}
+
 /// Given a valid two directions whose angle is 90, 180, or 270 degrees,
 /// return a convention string for the rotation
 ///
@@ -86,16 +192,91 @@ pub fn rotation_str(old: Direction, new: Direction) -> Result<&'static str, Erro
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A turn of a piece relative to the direction it is facing,
+/// named with the same convention as rotation_str
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Rotation {
+    Right,
+    UTurn,
+    Left,
+}
+
+impl Rotation {
+    /// The rotation that turns the old direction into the new one
+    pub fn between(old: Direction, new: Direction) -> Result<Rotation, Error> {
+        match rotation_str(old, new)? {
+            "R" => Ok(Rotation::Right),
+            "U" => Ok(Rotation::UTurn),
+            _ => Ok(Rotation::Left),
+        }
+    }
+
+    pub fn applied_to(self, direction: Direction) -> Direction {
+        match self {
+            Rotation::Right => direction.rotated_clockwise(),
+            Rotation::UTurn => direction.rotated_180(),
+            Rotation::Left => direction.rotated_counter_clockwise(),
+        }
+    }
+}
+
+impl Parseable for Rotation {
+    fn from_str(notation: &str) -> Result<Rotation, ParseError> {
+        match notation {
+            "R" => Ok(Rotation::Right),
+            "U" => Ok(Rotation::UTurn),
+            "L" => Ok(Rotation::Left),
+            _ => Err(ParseError::new(
+                ParseErrorKind::InvalidRotation(notation.to_string()),
+                0,
+            )),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        match self {
+            Rotation::Right => "R".to_string(),
+            Rotation::UTurn => "U".to_string(),
+            Rotation::Left => "L".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct StandardPiece {
     pub color: Color,
     pub kind: Kind,
     pub direction: Direction,
 }
 
+impl StandardPiece {
+    /// Based on the piece and its current orientation,
+    /// choose how to reflect a laser travelling in the incoming direction.
+    /// Returns None if the laser cannot be reflected and the piece is zapped
+    pub fn reflect(&self, incoming: Orthogonal) -> Option<Orthogonal> {
+        let diagonal = match (self.kind, self.direction) {
+            (Kind::Pawn, Direction::Diagonal(diagonal)) => diagonal,
+            // Monarchs can't reflect incoming lasers
+            _ => return None,
+        };
+
+        // The laser must be incoming opposite to one of the mirror faces,
+        // and leaves through the other face
+        let (a, b) = diagonal.decompose();
+        let reversed = incoming.opposite();
+        if reversed == a {
+            Some(b)
+        } else if reversed == b {
+            Some(a)
+        } else {
+            None
+        }
+    }
+}
+
 impl Parseable for StandardPiece {
-    fn from_str(notation: &str) -> StandardPiece {
-        match notation {
+    fn from_str(notation: &str) -> Result<StandardPiece, ParseError> {
+        let piece = match notation {
             "NN" => StandardPiece {
                 color: Color::White,
                 kind: Kind::Monarch,
@@ -176,88 +357,94 @@ impl Parseable for StandardPiece {
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::SouthEast),
             },
-            _ => panic!("Invalid piece notation"),
-        }
+            _ => {
+                return Err(ParseError::new(
+                    ParseErrorKind::InvalidPiece(notation.to_string()),
+                    0,
+                ))
+            }
+        };
+        Ok(piece)
     }
 
     fn to_string(&self) -> String {
-        match self {
-            &StandardPiece {
+        match *self {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::North),
             } => "NN".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::West),
             } => "WW".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::South),
             } => "SS".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::East),
             } => "EE".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::NorthWest),
             } => "NW".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::NorthEast),
             } => "NE".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::SouthWest),
             } => "SW".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::White,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::SouthEast),
             } => "SE".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::North),
             } => "nn".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::West),
             } => "ww".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::South),
             } => "ss".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Monarch,
                 direction: Direction::Orthogonal(Orthogonal::East),
             } => "ee".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::NorthWest),
             } => "nw".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::NorthEast),
             } => "ne".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::SouthWest),
             } => "sw".to_string(),
-            &StandardPiece {
+            StandardPiece {
                 color: Color::Black,
                 kind: Kind::Pawn,
                 direction: Direction::Diagonal(Diagonal::SouthEast),
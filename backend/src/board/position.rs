@@ -0,0 +1,78 @@
+use super::*;
+use crate::parser::*;
+use crate::zobrist;
+
+/// A board together with the player whose turn it is,
+/// written as the board's FEN followed by W or B
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Position<B> {
+    pub board: B,
+    pub player: Color,
+}
+
+impl<B> Position<B> {
+    pub fn new(board: B, player: Color) -> Self {
+        Position { board, player }
+    }
+}
+
+impl<const N: usize> Position<Grid<N>> {
+    /// Zobrist hash of the board and the player to move
+    pub fn zobrist(&self) -> u64 {
+        self.board.zobrist() ^ zobrist::player_key(self.player)
+    }
+}
+
+impl<B: Parseable> Parseable for Position<B> {
+    /// White moves first if the FEN does not say whose turn it is
+    fn from_str(input: &str) -> Result<Self, ParseError> {
+        let fen = input.trim_end();
+        let (board, player) = match fen.rsplit_once(char::is_whitespace) {
+            Some((board, token)) => match parse_current_player(token) {
+                (Some(player), "") => {
+                    let position = fen.len() - token.len();
+                    let player = Color::from_str(player).map_err(|e| e.offset(position))?;
+                    (board, player)
+                }
+                _ => (fen, Color::White),
+            },
+            None => (fen, Color::White),
+        };
+
+        Ok(Position {
+            board: B::from_str(board)?,
+            player,
+        })
+    }
+
+    fn to_string(&self) -> String {
+        format!("{} {}", self.board.to_string(), self.player.to_string())
+    }
+}
+
+#[cfg(test)]
+pub mod position_tests {
+    use super::*;
+
+    #[test]
+    pub fn round_trips_side_to_move() {
+        for fen in [
+            "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS W",
+            "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS B",
+        ] {
+            let position: Position<LeiserChessGrid> = Position::from_str(fen).unwrap();
+            assert_eq!(position.to_string(), fen);
+        }
+    }
+
+    #[test]
+    pub fn parses_lowercase_and_missing_player() {
+        let position: Position<LeiserChessGrid> =
+            Position::from_str("8/8/8/8/8/8/8/SS7 b").unwrap();
+        assert_eq!(position.player, Color::Black);
+        assert_eq!(position.to_string(), "8/8/8/8/8/8/8/SS7 B");
+
+        let position: Position<LeiserChessGrid> = Position::from_str("8/8/8/8/8/8/8/SS7").unwrap();
+        assert_eq!(position.player, Color::White);
+    }
+}
@@ -0,0 +1,247 @@
+use super::*;
+
+/// A transformation of a position into an equivalent one. The rules have
+/// no forward direction, so mirrored boards play out the same way, and
+/// swapping colors only renames the players
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// Reflect across the vertical axis, swapping east and west
+    MirrorHorizontal,
+    /// Reflect across the horizontal axis, swapping north and south
+    MirrorVertical,
+    /// Give the white pieces to black and the black pieces to white
+    SwapColors,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 3] = [
+        Symmetry::MirrorHorizontal,
+        Symmetry::MirrorVertical,
+        Symmetry::SwapColors,
+    ];
+}
+
+impl Direction {
+    /// The direction after the symmetry, so that a NE pawn
+    /// mirrored horizontally faces NW
+    pub fn transformed(self, symmetry: Symmetry) -> Direction {
+        use self::Diagonal::*;
+        use self::Orthogonal::*;
+
+        match (symmetry, self) {
+            (Symmetry::SwapColors, direction) => direction,
+            (Symmetry::MirrorHorizontal, Direction::Orthogonal(East)) => {
+                Direction::Orthogonal(West)
+            }
+            (Symmetry::MirrorHorizontal, Direction::Orthogonal(West)) => {
+                Direction::Orthogonal(East)
+            }
+            (Symmetry::MirrorHorizontal, Direction::Diagonal(NorthEast)) => {
+                Direction::Diagonal(NorthWest)
+            }
+            (Symmetry::MirrorHorizontal, Direction::Diagonal(NorthWest)) => {
+                Direction::Diagonal(NorthEast)
+            }
+            (Symmetry::MirrorHorizontal, Direction::Diagonal(SouthEast)) => {
+                Direction::Diagonal(SouthWest)
+            }
+            (Symmetry::MirrorHorizontal, Direction::Diagonal(SouthWest)) => {
+                Direction::Diagonal(SouthEast)
+            }
+            (Symmetry::MirrorVertical, Direction::Orthogonal(North)) => {
+                Direction::Orthogonal(South)
+            }
+            (Symmetry::MirrorVertical, Direction::Orthogonal(South)) => {
+                Direction::Orthogonal(North)
+            }
+            (Symmetry::MirrorVertical, Direction::Diagonal(NorthEast)) => {
+                Direction::Diagonal(SouthEast)
+            }
+            (Symmetry::MirrorVertical, Direction::Diagonal(SouthEast)) => {
+                Direction::Diagonal(NorthEast)
+            }
+            (Symmetry::MirrorVertical, Direction::Diagonal(NorthWest)) => {
+                Direction::Diagonal(SouthWest)
+            }
+            (Symmetry::MirrorVertical, Direction::Diagonal(SouthWest)) => {
+                Direction::Diagonal(NorthWest)
+            }
+            // Directions along the mirror stay as they are
+            (_, direction) => direction,
+        }
+    }
+}
+
+impl Rotation {
+    /// The rotation after the symmetry, as mirrors turn clockwise
+    /// rotations into counter clockwise ones
+    pub fn transformed(self, symmetry: Symmetry) -> Rotation {
+        match (symmetry, self) {
+            (Symmetry::SwapColors, rotation) => rotation,
+            (_, Rotation::Right) => Rotation::Left,
+            (_, Rotation::Left) => Rotation::Right,
+            (_, Rotation::UTurn) => Rotation::UTurn,
+        }
+    }
+}
+
+impl StandardPiece {
+    pub fn transformed(self, symmetry: Symmetry) -> StandardPiece {
+        StandardPiece {
+            color: match symmetry {
+                Symmetry::SwapColors => self.color.opposite(),
+                _ => self.color,
+            },
+            kind: self.kind,
+            direction: self.direction.transformed(symmetry),
+        }
+    }
+}
+
+impl GridLocation {
+    /// The location after the symmetry, on a board
+    /// with the given number of rows and columns
+    pub fn transformed(self, symmetry: Symmetry, size: usize) -> GridLocation {
+        match symmetry {
+            Symmetry::MirrorHorizontal => GridLocation {
+                x: size - 1 - self.x,
+                y: self.y,
+            },
+            Symmetry::MirrorVertical => GridLocation {
+                x: self.x,
+                y: size - 1 - self.y,
+            },
+            Symmetry::SwapColors => self,
+        }
+    }
+}
+
+impl<const N: usize> Grid<N> {
+    /// The board with the symmetry applied to every piece and square
+    pub fn transformed(&self, symmetry: Symmetry) -> Grid<N> {
+        let mut grid = Grid::empty();
+        for (location, piece) in self.pieces() {
+            grid.set_unchecked(
+                &location.transformed(symmetry, N),
+                piece.transformed(symmetry),
+            );
+        }
+        grid
+    }
+}
+
+impl<const N: usize> Position<Grid<N>> {
+    /// The position with the symmetry applied to the board,
+    /// handing the move to the other player when colors are swapped
+    pub fn transformed(&self, symmetry: Symmetry) -> Self {
+        let player = match symmetry {
+            Symmetry::SwapColors => self.player.opposite(),
+            _ => self.player,
+        };
+        Position::new(self.board.transformed(symmetry), player)
+    }
+
+    /// The smallest FEN among every equivalent position, along with the
+    /// symmetries that lead to it. The symmetries undo themselves, so
+    /// applying them again to an action on the canonical position gives
+    /// the matching action on this one
+    pub fn canonical(&self) -> (Self, Vec<Symmetry>) {
+        (0..1 << Symmetry::ALL.len())
+            .map(|mask: usize| {
+                let symmetries: Vec<Symmetry> = Symmetry::ALL
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(bit, _)| mask & 1 << bit != 0)
+                    .map(|(_, symmetry)| symmetry)
+                    .collect();
+                let position = symmetries.iter().fold(self.clone(), |position, symmetry| {
+                    position.transformed(*symmetry)
+                });
+                (position, symmetries)
+            })
+            .min_by_key(|(position, _)| position.to_string())
+            .expect("There is always the position itself")
+    }
+}
+
+#[cfg(test)]
+pub mod symmetry_tests {
+    use super::*;
+    use crate::constants::STARTING_POSITION;
+
+    #[test]
+    pub fn mirrors_turn_directions() {
+        let pawn = Direction::Diagonal(Diagonal::NorthEast);
+        assert_eq!(
+            pawn.transformed(Symmetry::MirrorHorizontal),
+            Direction::Diagonal(Diagonal::NorthWest)
+        );
+        assert_eq!(
+            pawn.transformed(Symmetry::MirrorVertical),
+            Direction::Diagonal(Diagonal::SouthEast)
+        );
+        let monarch = Direction::Orthogonal(Orthogonal::North);
+        assert_eq!(monarch.transformed(Symmetry::MirrorHorizontal), monarch);
+        assert_eq!(
+            Rotation::Right.transformed(Symmetry::MirrorVertical),
+            Rotation::Left
+        );
+
+        // Turning and then mirroring ends up where mirroring
+        // and then making the mirrored turn does
+        for direction in [pawn, monarch] {
+            for rotation in [Rotation::Right, Rotation::Left, Rotation::UTurn] {
+                for symmetry in Symmetry::ALL {
+                    assert_eq!(
+                        rotation.applied_to(direction).transformed(symmetry),
+                        rotation
+                            .transformed(symmetry)
+                            .applied_to(direction.transformed(symmetry))
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn transforms_boards() {
+        let grid = LeiserChessGrid::from_str("nn6ne/8/8/3NE4/8/8/8/SS7").unwrap();
+        assert_eq!(
+            grid.transformed(Symmetry::MirrorHorizontal).to_string(),
+            "nw6nn/8/8/4NW3/8/8/8/7SS"
+        );
+        assert_eq!(
+            grid.transformed(Symmetry::MirrorVertical).to_string(),
+            "NN7/8/8/8/3SE4/8/8/ss6se"
+        );
+        assert_eq!(
+            grid.transformed(Symmetry::SwapColors).to_string(),
+            "NN6NE/8/8/3ne4/8/8/8/ss7"
+        );
+        for symmetry in Symmetry::ALL {
+            let transformed = grid.transformed(symmetry);
+            assert_eq!(transformed.transformed(symmetry), grid);
+        }
+
+        // Black's view of the opening is white's, mirrored
+        let opening = Position::<LeiserChessGrid>::from_str(STARTING_POSITION).unwrap();
+        let flipped = opening
+            .transformed(Symmetry::MirrorVertical)
+            .transformed(Symmetry::SwapColors);
+        assert_eq!(flipped.board, opening.board);
+        assert_eq!(flipped.player, Color::Black);
+    }
+
+    #[test]
+    pub fn equivalent_positions_share_a_canonical_form() {
+        let position = Position::<LeiserChessGrid>::from_str("nn6ne/8/8/3NE4/8/8/8/SS7 W").unwrap();
+        let (canonical, symmetries) = position.canonical();
+        for symmetry in Symmetry::ALL {
+            assert_eq!(position.transformed(symmetry).canonical().0, canonical);
+        }
+        let restored = symmetries.iter().fold(canonical, |position, symmetry| {
+            position.transformed(*symmetry)
+        });
+        assert_eq!(restored, position);
+    }
+}
@@ -1,3 +1,10 @@
+/// Number of rows and columns on the board
+pub const BOARD_SIZE: usize = 8;
+
+/// Largest number of rows and columns a board may have,
+/// limited by the zobrist keys there are for each square
+pub const MAX_BOARD_SIZE: usize = 16;
+
 /// Theoretical upper bound on the number of actions that can be taken in a game.
 pub const MAX_ACTIONS: usize = 100;
 
@@ -5,6 +12,10 @@ pub const MAX_ACTIONS: usize = 100;
 /// that can be reached before a game terminates
 pub const MAX_HISTORY_LENGTH: usize = 400;
 
+/// Most actions a game may have, as the initial board
+/// counts towards the history as well
+pub const MAX_MOVES: usize = MAX_HISTORY_LENGTH - 1;
+
 /// Theoretical upper bound on the number of pieces that can be removed
 /// from the board in one action
 pub const MAX_VICTIMS: usize = 3;
@@ -12,3 +23,6 @@ pub const MAX_VICTIMS: usize = 3;
 /// Theoretical upper bound on the number of pieces that can on
 /// the board at any given time
 pub const MAX_PIECES: usize = 32;
+
+/// The position every game starts from, with white to move
+pub const STARTING_POSITION: &str = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS W";
@@ -0,0 +1,185 @@
+use crate::board::*;
+use crate::laser::{self, LaserPath};
+
+/// Weights of every term of the evaluation, in hundredths of a pawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalParams {
+    pub monarch_value: i32,
+    pub pawn_value: i32,
+    /// Bonus for a monarch on one of the central squares,
+    /// shrinking in proportion to its qi
+    pub monarch_centrality: i32,
+    /// Bonus for a pawn on one of the central squares,
+    /// shrinking in proportion to its qi
+    pub pawn_centrality: i32,
+    /// Penalty for each monarch an enemy laser currently hits
+    pub monarch_in_laser: i32,
+    /// Penalty for each pawn an enemy laser currently hits
+    pub pawn_in_laser: i32,
+    /// Penalty for each square next to a monarch that an enemy laser crosses
+    pub monarch_exposure: i32,
+    /// Bonus for each square our lasers cross
+    pub laser_coverage: i32,
+    /// Bonus for each pawn, of either color, that reflects our lasers
+    pub mirror_control: i32,
+    /// Bonus for each empty square next to one of our pawns
+    pub pawn_mobility: i32,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            monarch_value: 500,
+            pawn_value: 100,
+            monarch_centrality: 10,
+            pawn_centrality: 20,
+            monarch_in_laser: 300,
+            pawn_in_laser: 40,
+            monarch_exposure: 15,
+            laser_coverage: 2,
+            mirror_control: 8,
+            pawn_mobility: 2,
+        }
+    }
+}
+
+/// Score of the board from the perspective of the given player,
+/// positive when they are better off
+pub fn evaluate<const N: usize>(board: &Grid<N>, player: Color, params: &EvalParams) -> i32 {
+    evaluate_side(board, player, params) - evaluate_side(board, player.opposite(), params)
+}
+
+/// Every term of the evaluation that favors the given color
+fn evaluate_side<const N: usize>(board: &Grid<N>, color: Color, params: &EvalParams) -> i32 {
+    let own_paths = laser_paths(board, color);
+    let enemy_paths = laser_paths(board, color.opposite());
+    // The corners are the furthest any square is from the center
+    let max_qi = GridLocation { x: 0, y: 0 }.qi(N);
+
+    let mut score = 0;
+    for (location, piece) in board.pieces().filter(|(_, p)| p.color == color) {
+        let centrality = max_qi - location.qi(N);
+        score += match piece.kind {
+            Kind::Monarch => {
+                params.monarch_value + params.monarch_centrality * centrality / max_qi
+                    - params.monarch_exposure * exposure(location, &enemy_paths)
+            }
+            Kind::Pawn => {
+                params.pawn_value
+                    + params.pawn_centrality * centrality / max_qi
+                    + params.pawn_mobility * mobility(board, location)
+            }
+        };
+    }
+
+    for path in &enemy_paths {
+        if let Some(piece) = path.target.and_then(|t| board.get_unchecked(&t)) {
+            if piece.color == color {
+                score -= match piece.kind {
+                    Kind::Monarch => params.monarch_in_laser,
+                    Kind::Pawn => params.pawn_in_laser,
+                };
+            }
+        }
+    }
+
+    for path in &own_paths {
+        // The first square is the monarch firing the laser
+        let crossed = path.squares.len() as i32 - 1;
+        let mirrors = path.squares[1..]
+            .iter()
+            .filter(|square| Some(**square) != path.target)
+            .filter(|square| board.get_unchecked(square).is_some())
+            .count() as i32;
+        score += params.laser_coverage * crossed + params.mirror_control * mirrors;
+    }
+    score
+}
+
+fn laser_paths<const N: usize>(board: &Grid<N>, color: Color) -> Vec<LaserPath> {
+    laser::lasers(board, color)
+        .iter()
+        .map(|laser| laser.path_on(board))
+        .collect()
+}
+
+/// Number of squares next to the location crossed by any of the paths
+fn exposure(location: GridLocation, paths: &[LaserPath]) -> i32 {
+    paths
+        .iter()
+        .flat_map(|path| path.squares.iter())
+        .filter(|square| square.is_adjacent(&location))
+        .count() as i32
+}
+
+/// Number of empty squares next to the location
+fn mobility<const N: usize>(board: &Grid<N>, location: GridLocation) -> i32 {
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+        .filter_map(|(dx, dy)| location.offset(dx, dy, N))
+        .filter(|neighbor| *neighbor != location && board.get_unchecked(neighbor).is_none())
+        .count() as i32
+}
+
+#[cfg(test)]
+pub mod eval_tests {
+    use super::*;
+
+    fn evaluate_fen(fen: &str, player: Color) -> i32 {
+        let grid = LeiserChessGrid::from_str(fen).unwrap();
+        evaluate(&grid, player, &EvalParams::default())
+    }
+
+    #[test]
+    pub fn opening_is_balanced() {
+        let opening = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS";
+        assert_eq!(evaluate_fen(opening, Color::White), 0);
+        assert_eq!(evaluate_fen(opening, Color::Black), 0);
+    }
+
+    #[test]
+    pub fn material_and_centrality_count() {
+        let params = EvalParams::default();
+        // White has an extra pawn
+        let score = evaluate_fen("nn6NN/8/8/8/8/8/8/SS5NE1", Color::White);
+        assert!(score > params.pawn_value / 2);
+        assert_eq!(
+            score,
+            -evaluate_fen("nn6NN/8/8/8/8/8/8/SS5NE1", Color::Black)
+        );
+
+        let central = evaluate_fen("nn6NN/8/8/3NE4/8/8/8/SS7", Color::White);
+        let edge = evaluate_fen("nn6NN/8/8/8/8/8/8/SS5NE1", Color::White);
+        assert!(central > edge + params.pawn_centrality / 2);
+    }
+
+    #[test]
+    pub fn monarchs_in_laser_paths_are_penalized() {
+        // The black monarch on a1 faces south towards the white monarch on a8
+        let threatened = evaluate_fen("ss7/8/8/8/8/8/8/SS6NN", Color::White);
+        let safe = evaluate_fen("ee7/8/8/8/8/8/8/SS6NN", Color::White);
+        assert!(threatened < safe - EvalParams::default().monarch_in_laser / 2);
+    }
+
+    #[test]
+    pub fn evaluation_is_symmetric() {
+        let params = EvalParams::default();
+        for fen in [
+            "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS",
+            "ss7/8/2nw5/8/5SE2/8/8/SS6NN",
+        ] {
+            let grid = LeiserChessGrid::from_str(fen).unwrap();
+            for player in [Color::White, Color::Black] {
+                let score = evaluate(&grid, player, &params);
+                for symmetry in [Symmetry::MirrorHorizontal, Symmetry::MirrorVertical] {
+                    assert_eq!(
+                        evaluate(&grid.transformed(symmetry), player, &params),
+                        score
+                    );
+                }
+                let swapped = grid.transformed(Symmetry::SwapColors);
+                assert_eq!(evaluate(&swapped, player.opposite(), &params), score);
+            }
+        }
+    }
+}
@@ -1,18 +1,537 @@
-use super::*;
+use super::{Error, GameResult, Reason, ResultConfig};
+use crate::action::generation::generate_actions;
+use crate::action::standard::*;
 use crate::action::*;
 use crate::board::*;
 use crate::constants::*;
+use crate::laser;
+use crate::rules::RuleSet;
+use crate::zobrist;
 
-pub trait GameBoard: Indexable + OptimizedIndexable + Parseable + HumanReadable + Default {}
-pub trait GameAction<T: Indexable>: OptimizedAction<T> + Parseable + Default {}
+pub trait GameBoard: Indexable + OptimizedIndexable + Parseable + HumanReadable + Default {
+    /// Fire the lasers of every monarch of the given color under the given
+    /// rules, returning the zapped pieces in the order they were removed
+    fn fire_lasers(&mut self, color: Color, rules: &RuleSet) -> Vec<(Self::Location, Self::Piece)>;
 
-/// TODO: Somehow this structure should unify the interfaces
-/// of Optimized and non optimized interfaces so that it is easy to
-/// test both implementations, or switch between them
+    /// Hash of the pieces on the board, ignoring whose turn it is
+    fn board_hash(&self) -> u64;
+
+    /// Number of monarchs of the given color still on the board
+    fn monarch_count(&self, color: Color) -> usize;
+}
+
+pub trait GameAction<T: Indexable>: Action<T> + Parseable + Clone {
+    /// The player whose piece would take the action on the given board,
+    /// or None if there is no piece to take it
+    fn player(&self, board: &T) -> Option<Color>;
+
+    /// Every action the given player could take on the board under the given rules
+    fn legal_actions(board: &T, player: Color, rules: &RuleSet) -> Vec<Self>;
+}
+
+/// Pieces removed by lasers and where they stood
+pub type Zapped<B> = Vec<(
+    <B as OptimizedIndexable>::Location,
+    <B as OptimizedIndexable>::Piece,
+)>;
+
+/// A game played with standard actions on a board of any size
+pub type GridGame<const N: usize> = Game<Grid<N>, StandardAction>;
+
+/// A game played with the standard board and actions
+pub type StandardGame = GridGame<BOARD_SIZE>;
+
+impl<const N: usize> GameBoard for Grid<N> {
+    fn fire_lasers(&mut self, color: Color, rules: &RuleSet) -> Vec<(GridLocation, StandardPiece)> {
+        laser::fire_lasers(self, color, rules)
+    }
+
+    fn board_hash(&self) -> u64 {
+        self.zobrist()
+    }
+
+    fn monarch_count(&self, color: Color) -> usize {
+        self.pieces()
+            .filter(|(_, piece)| piece.color == color && matches!(piece.kind, Kind::Monarch))
+            .count()
+    }
+}
+
+impl<const N: usize> GameAction<Grid<N>> for StandardAction {
+    fn player(&self, board: &Grid<N>) -> Option<Color> {
+        board
+            .get(&self.source())
+            .ok()
+            .flatten()
+            .map(|piece| piece.color)
+    }
+
+    fn legal_actions(board: &Grid<N>, player: Color, rules: &RuleSet) -> Vec<Self> {
+        generate_actions(board, player, rules)
+    }
+}
+
+/// Everything that happened during a single turn,
+/// enough to take it back
+struct Turn<B: GameBoard, A: GameAction<B>> {
+    action: A,
+    undo: A::Undo,
+    zapped: Zapped<B>,
+    /// Hash of the board after the turn
+    hash: u64,
+}
+
+/// A game in progress, in which players take turns applying actions
+/// to a board and the mover's lasers fire after every action.
+///
+/// Generic over the board and action so that optimized and
+/// non optimized implementations share the same interface
 pub struct Game<B: GameBoard, A: GameAction<B>> {
-    current_board: B,
-    history: [B; MAX_HISTORY_LENGTH],
-    history_length: usize,
-    actions: [A; MAX_ACTIONS],
-    actions_length: usize,
+    board: B,
+    current_player: Color,
+    history: Vec<Turn<B, A>>,
+    /// The board and player to move before any action was taken
+    initial: Position<B>,
+    /// Hash of the board before any action was taken
+    initial_hash: u64,
+    rules: RuleSet,
+}
+
+impl<B: GameBoard, A: GameAction<B>> Clone for Turn<B, A>
+where
+    A::Undo: Clone,
+    Zapped<B>: Clone,
+{
+    fn clone(&self) -> Self {
+        Turn {
+            action: self.action.clone(),
+            undo: self.undo.clone(),
+            zapped: self.zapped.clone(),
+            hash: self.hash,
+        }
+    }
+}
+
+impl<B: GameBoard, A: GameAction<B>> Clone for Game<B, A>
+where
+    A::Undo: Clone,
+    Zapped<B>: Clone,
+{
+    fn clone(&self) -> Self {
+        Game {
+            board: self.board.clone(),
+            current_player: self.current_player,
+            history: self.history.clone(),
+            initial: self.initial.clone(),
+            initial_hash: self.initial_hash,
+            rules: self.rules,
+        }
+    }
+}
+
+impl<B: GameBoard, A: GameAction<B>> Game<B, A> {
+    pub fn new(board: B, current_player: Color) -> Self {
+        Game::with_rules(board, current_player, RuleSet::default())
+    }
+
+    pub fn with_rules(board: B, current_player: Color, rules: RuleSet) -> Self {
+        Game {
+            initial_hash: board.board_hash(),
+            initial: Position::new(board.clone(), current_player),
+            board,
+            current_player,
+            history: Vec::new(),
+            rules,
+        }
+    }
+
+    pub fn from_position(position: Position<B>) -> Self {
+        Game::new(position.board, position.player)
+    }
+
+    /// Start a game from a FEN string such as those written by the frontend's
+    /// GameState.toFEN, where the final W or B names the player to move
+    pub fn from_fen(fen: &str) -> Result<Self, ParseError> {
+        Ok(Game::from_position(Position::from_str(fen)?))
+    }
+
+    /// The rules every action of the game is played by
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// The board and the player to move before any action was taken
+    pub fn initial_position(&self) -> &Position<B> {
+        &self.initial
+    }
+
+    /// The current board and the player to move
+    pub fn position(&self) -> Position<B> {
+        Position::new(self.board.clone(), self.current_player)
+    }
+
+    pub fn to_fen(&self) -> String {
+        format!(
+            "{} {}",
+            self.board.to_string(),
+            self.current_player.to_string()
+        )
+    }
+
+    pub fn board(&self) -> &B {
+        &self.board
+    }
+
+    pub fn current_player(&self) -> Color {
+        self.current_player
+    }
+
+    /// Actions taken so far, oldest first
+    pub fn actions(&self) -> impl Iterator<Item = &A> {
+        self.history.iter().map(|turn| &turn.action)
+    }
+
+    /// Hash of the current board and the player to move
+    pub fn zobrist(&self) -> u64 {
+        self.board.board_hash() ^ zobrist::player_key(self.current_player)
+    }
+
+    /// Pieces zapped by the lasers fired after the most recent action
+    pub fn last_zapped(&self) -> &[(B::Location, B::Piece)] {
+        self.history.last().map_or(&[], |turn| &turn.zapped)
+    }
+
+    /// Number of actions taken so far
+    pub fn history_length(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Decide whether the game is over, and why
+    pub fn result(&self, config: &ResultConfig) -> GameResult {
+        let white = self.board.monarch_count(Color::White);
+        let black = self.board.monarch_count(Color::Black);
+        match (white, black) {
+            (0, 0) => {
+                return GameResult::Draw {
+                    reason: Reason::MutualDestruction,
+                }
+            }
+            (0, _) => {
+                return GameResult::Win {
+                    winner: Color::Black,
+                    reason: Reason::MonarchsZapped,
+                }
+            }
+            (_, 0) => {
+                return GameResult::Win {
+                    winner: Color::White,
+                    reason: Reason::MonarchsZapped,
+                }
+            }
+            _ => {}
+        }
+
+        if self.occurrences() >= config.repetition_limit {
+            return GameResult::Draw {
+                reason: Reason::Repetition,
+            };
+        }
+        if self.history.len() >= config.move_limit.min(MAX_MOVES) {
+            return GameResult::Draw {
+                reason: Reason::MoveLimit,
+            };
+        }
+        GameResult::InProgress
+    }
+
+    /// Number of times the current board has occurred
+    /// with the current player to move, including now
+    fn occurrences(&self) -> usize {
+        let hash = self
+            .history
+            .last()
+            .map_or(self.initial_hash, |turn| turn.hash);
+        std::iter::once(self.initial_hash)
+            .chain(self.history.iter().map(|turn| turn.hash))
+            .rev()
+            // The same player is to move every other turn
+            .step_by(2)
+            .filter(|&previous| previous == hash)
+            .count()
+    }
+
+    /// Every action the current player could take
+    /// that does not break the repetition rule
+    pub fn legal_actions(&self) -> Vec<A> {
+        let mut board = self.board.clone();
+        A::legal_actions(&self.board, self.current_player, &self.rules)
+            .into_iter()
+            .filter(|action| {
+                let turn = Self::play(&mut board, action, self.current_player, &self.rules);
+                let (undo, zapped) = match turn {
+                    Ok(turn) => turn,
+                    Err(_) => return false,
+                };
+                let repeats = self.repeats(board.board_hash());
+                Self::take_back(&mut board, action, &undo, zapped);
+                !repeats
+            })
+            .collect()
+    }
+
+    /// Apply an action for the current player, then fire their lasers
+    /// and pass the turn to the other player.
+    ///
+    /// The resulting board may not match either of the previous two boards
+    pub fn apply(&mut self, action: A) -> Result<(), Error> {
+        match action.player(&self.board) {
+            Some(player) if player != self.current_player => {
+                return Err(Error::WrongPlayer(player))
+            }
+            _ => {}
+        }
+        if self.history.len() >= MAX_MOVES {
+            return Err(Error::HistoryFull);
+        }
+
+        let (undo, zapped) =
+            Self::play(&mut self.board, &action, self.current_player, &self.rules)?;
+        let hash = self.board.board_hash();
+        if self.repeats(hash) {
+            Self::take_back(&mut self.board, &action, &undo, zapped);
+            return Err(Error::Repetition);
+        }
+
+        self.history.push(Turn {
+            action,
+            undo,
+            zapped,
+            hash,
+        });
+        self.current_player = self.current_player.opposite();
+        Ok(())
+    }
+
+    /// Take back the most recent action, returning it
+    pub fn undo(&mut self) -> Result<A, Error> {
+        let Turn {
+            action,
+            undo,
+            zapped,
+            ..
+        } = self.history.pop().ok_or(Error::NothingToUndo)?;
+
+        Self::take_back(&mut self.board, &action, &undo, zapped);
+        self.current_player = self.current_player.opposite();
+        Ok(action)
+    }
+
+    /// Apply the action and fire the mover's lasers
+    fn play(
+        board: &mut B,
+        action: &A,
+        player: Color,
+        rules: &RuleSet,
+    ) -> Result<(A::Undo, Zapped<B>), Error> {
+        let undo = action.apply(board, rules)?;
+        let zapped = board.fire_lasers(player, rules);
+        Ok((undo, zapped))
+    }
+
+    /// Restore the zapped pieces, then revert the action
+    fn take_back(board: &mut B, action: &A, undo: &A::Undo, zapped: Zapped<B>) {
+        for (location, piece) in zapped.into_iter().rev() {
+            board.set_unchecked(&location, piece);
+        }
+        action.unapply_unchecked(board, undo);
+    }
+
+    /// True if the hash matches either of the previous two boards
+    fn repeats(&self, hash: u64) -> bool {
+        std::iter::once(self.initial_hash)
+            .chain(self.history.iter().map(|turn| turn.hash))
+            .rev()
+            .take(2)
+            .any(|previous| previous == hash)
+    }
+
+    /// Rewind the game to just after the action with the given
+    /// (zero based) index was taken, discarding every later action.
+    /// Returns false if no such action has been taken
+    pub fn go_to_move(&mut self, move_number: usize) -> bool {
+        if move_number >= self.history.len() {
+            return false;
+        }
+        while self.history.len() > move_number + 1 {
+            self.undo().expect("History cannot be empty");
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+pub mod game_tests {
+    use super::*;
+
+    fn opening() -> StandardGame {
+        Game::from_fen("nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS W").unwrap()
+    }
+
+    #[test]
+    pub fn players_alternate() {
+        let mut game = opening();
+        let action = game.legal_actions()[0].clone();
+        game.apply(action.clone()).unwrap();
+        assert_eq!(game.current_player(), Color::Black);
+        assert!(matches!(game.apply(action), Err(Error::WrongPlayer(_))));
+
+        let action = game.legal_actions()[0].clone();
+        game.apply(action).unwrap();
+        assert_eq!(game.current_player(), Color::White);
+        assert_eq!(game.history_length(), 2);
+        assert!(game.to_fen().ends_with(" W"));
+    }
+
+    #[test]
+    pub fn lasers_fire_after_action() {
+        let initial = LeiserChessGrid::from_str("nn7/8/8/8/8/8/8/SS7").unwrap();
+        let mut game: StandardGame = Game::new(initial.clone(), Color::White);
+        game.apply(StandardAction::from_str("a8U").unwrap())
+            .unwrap();
+        assert_eq!(game.board().to_string(), "8/8/8/8/8/8/8/NN7");
+
+        game.undo().unwrap();
+        assert_eq!(game.board(), &initial);
+        assert_eq!(game.current_player(), Color::White);
+        assert!(matches!(game.undo(), Err(Error::NothingToUndo)));
+    }
+
+    #[test]
+    pub fn repetition_is_rejected() {
+        let mut game = opening();
+        let actions = game.legal_actions();
+        // Null moves that zap nothing leave the board unchanged
+        assert_eq!(actions.len(), 64);
+        assert!(actions
+            .iter()
+            .all(|action| action.kind(game.board()) != ActionKind::Null));
+
+        let null = StandardAction::from_str("a8a8").unwrap();
+        assert!(matches!(game.apply(null), Err(Error::Repetition)));
+        assert_eq!(game.history_length(), 0);
+    }
+
+    #[test]
+    pub fn detects_wins() {
+        let mut game: StandardGame = Game::from_fen("nn7/8/8/8/8/8/8/SS6ss W").unwrap();
+        let config = ResultConfig::default();
+        assert_eq!(game.result(&config), GameResult::InProgress);
+
+        game.apply(StandardAction::from_str("a8U").unwrap())
+            .unwrap();
+        assert_eq!(game.result(&config), GameResult::InProgress);
+
+        // Without the monarch on h8, zapping a1 leaves black with no monarchs
+        let mut game: StandardGame = Game::from_fen("nn7/8/8/8/8/8/8/SS7 W").unwrap();
+        game.apply(StandardAction::from_str("a8U").unwrap())
+            .unwrap();
+        assert_eq!(
+            game.result(&config),
+            GameResult::Win {
+                winner: Color::White,
+                reason: Reason::MonarchsZapped,
+            }
+        );
+    }
+
+    #[test]
+    pub fn detects_draws() {
+        let mut game: StandardGame = Game::from_fen("nn7/8/8/3se4/4NE3/8/8/SS7 W").unwrap();
+        let config = ResultConfig {
+            move_limit: 100,
+            repetition_limit: 3,
+        };
+        // Both players spin a pawn round in circles
+        for ply in 0..16 {
+            assert_eq!(game.result(&config), GameResult::InProgress);
+            let action = match ply % 2 {
+                0 => StandardAction::from_str("e5R").unwrap(),
+                _ => StandardAction::from_str("d4R").unwrap(),
+            };
+            game.apply(action).unwrap();
+        }
+        assert_eq!(
+            game.result(&config),
+            GameResult::Draw {
+                reason: Reason::Repetition,
+            }
+        );
+
+        let config = ResultConfig {
+            move_limit: 16,
+            repetition_limit: 4,
+        };
+        assert_eq!(
+            game.result(&config),
+            GameResult::Draw {
+                reason: Reason::MoveLimit,
+            }
+        );
+
+        // Limits beyond what a game can hold end it once it is full
+        let config = ResultConfig {
+            move_limit: MAX_MOVES * 2,
+            repetition_limit: usize::MAX,
+        };
+        for ply in 16.. {
+            let action = match ply % 2 {
+                0 => StandardAction::from_str("e5R").unwrap(),
+                _ => StandardAction::from_str("d4R").unwrap(),
+            };
+            if game.apply(action).is_err() {
+                break;
+            }
+        }
+        assert_eq!(game.history_length(), MAX_MOVES);
+        assert_eq!(
+            game.result(&config),
+            GameResult::Draw {
+                reason: Reason::MoveLimit,
+            }
+        );
+    }
+
+    #[test]
+    pub fn games_follow_their_rules() {
+        let board = LeiserChessGrid::from_str("8/8/2NN5/3ss4/8/8/8/8").unwrap();
+        let shove = StandardAction::from_str("c3d4").unwrap();
+        let mut game: StandardGame = Game::new(board.clone(), Color::White);
+        assert!(matches!(
+            game.apply(shove.clone()),
+            Err(Error::InvalidAction(_))
+        ));
+
+        let mut game: StandardGame = Game::with_rules(board, Color::White, RuleSet::RELAXED);
+        assert_eq!(game.rules(), &RuleSet::RELAXED);
+        assert!(game.legal_actions().contains(&shove));
+        game.apply(shove).unwrap();
+        assert_eq!(game.board().to_string(), "8/8/8/3NN4/4ss3/8/8/8");
+    }
+
+    #[test]
+    pub fn go_to_move_rewinds_history() {
+        let mut game = opening();
+        let mut boards = vec![];
+        for _ in 0..4 {
+            let action = game.legal_actions()[5].clone();
+            game.apply(action).unwrap();
+            boards.push(game.board().clone());
+        }
+
+        assert!(!game.go_to_move(4));
+        assert!(game.go_to_move(1));
+        assert_eq!(game.history_length(), 2);
+        assert_eq!(game.board(), &boards[1]);
+        assert_eq!(game.current_player(), Color::White);
+    }
 }
@@ -1,3 +1,28 @@
+#[allow(clippy::module_inception)]
 mod game;
+mod record;
+mod result;
 
 pub use game::*;
+pub use record::*;
+pub use result::*;
+
+use crate::action;
+use crate::board::{Color, ParseError};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    InvalidAction(#[from] action::Error),
+    #[error("It is not {0:?}'s turn")]
+    WrongPlayer(Color),
+    #[error("Game has reached the maximum history length")]
+    HistoryFull,
+    #[error("Action repeats one of the previous two boards")]
+    Repetition,
+    #[error("No actions to undo")]
+    NothingToUndo,
+    #[error("{0}")]
+    InvalidPosition(#[from] ParseError),
+}
@@ -0,0 +1,443 @@
+use super::{Error, Game, GameResult, ResultConfig, StandardGame};
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Name of the tag holding the starting position
+const FEN_TAG: &str = "FEN";
+
+/// Name of the tag holding the outcome of the game
+const RESULT_TAG: &str = "Result";
+
+/// A tag pair such as [Event "Finals"], with quotes and backslashes escaped
+static TAG_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^\[([A-Za-z0-9_]+)\s+"((?:[^"\\]|\\.)*)"\]\s*$"#).unwrap());
+
+/// Written in place of the missing first action when black moves first,
+/// just like the frontend's move history
+const PLACEHOLDER: &str = "...";
+
+/// Marks written after the actions of a game, depending on how it ended
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// A named piece of information about a game, such as
+/// the players, the date or the time control
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tag {
+    pub name: String,
+    pub value: String,
+}
+
+/// Everything needed to replay a game: where it started
+/// and every action taken since, oldest first.
+///
+/// Written in a PGN style format, such as
+///
+/// ```text
+/// [Event "Club match"]
+/// [White "Alice"]
+/// [Black "Bob"]
+/// [Result "*"]
+/// [FEN "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS W"]
+/// [TimeControl "300+2"]
+///
+/// 1. a7b6 a2b3
+/// 2. h8L *
+/// ```
+///
+/// where the actions are numbered in pairs, and the first action
+/// is written as ... when black moves first
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    /// Tags other than the starting position, in the order they are written
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Vec<Tag>,
+    /// FEN of the starting position, including the player to move
+    pub start: String,
+    pub actions: Vec<StandardAction>,
+}
+
+/// What is wrong with a game record
+#[derive(thiserror::Error, Debug)]
+pub enum RecordErrorKind {
+    #[error("Malformed tag pair")]
+    MalformedTag,
+    #[error("{0}")]
+    InvalidPosition(ParseErrorKind),
+    #[error("{0}")]
+    InvalidAction(ParseErrorKind),
+    #[error("Expected move number {0}")]
+    ExpectedMoveNumber(usize),
+    #[error("Unexpected move number {0}")]
+    UnexpectedMoveNumber(String),
+    #[error("Expected ... as black moves first")]
+    ExpectedPlaceholder,
+    #[error("Unexpected {0}")]
+    UnexpectedToken(String),
+    #[error("Illegal action {action}: {error}")]
+    IllegalAction { action: String, error: Error },
+}
+
+/// Reason a game record could not be read, along with
+/// the line and column, both starting at 1, where it was found
+#[derive(thiserror::Error, Debug)]
+#[error("{kind} at line {line}, column {column}")]
+pub struct RecordError {
+    pub kind: RecordErrorKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl RecordError {
+    fn new(kind: RecordErrorKind, line: usize, column: usize) -> Self {
+        RecordError { kind, line, column }
+    }
+}
+
+/// The result tag value describing the state of a game
+fn result_mark(result: GameResult) -> &'static str {
+    match result {
+        GameResult::InProgress => "*",
+        GameResult::Win {
+            winner: Color::White,
+            ..
+        } => "1-0",
+        GameResult::Win {
+            winner: Color::Black,
+            ..
+        } => "0-1",
+        GameResult::Draw { .. } => "1/2-1/2",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+/// Number of characters in the line before the given byte offset
+fn column_of(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count()
+}
+
+/// Split the line on whitespace, pairing every token with
+/// the column it starts at, counted in characters from 1
+fn tokens_with_columns(line: &str) -> Vec<(&str, usize)> {
+    let mut tokens = Vec::new();
+    // Byte offset and column of the token being read, if any
+    let mut current = None;
+    for (column, (offset, c)) in line.char_indices().enumerate() {
+        match (c.is_whitespace(), current) {
+            (false, None) => current = Some((offset, column + 1)),
+            (true, Some((start, start_column))) => {
+                tokens.push((&line[start..offset], start_column));
+                current = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((start, start_column)) = current {
+        tokens.push((&line[start..], start_column));
+    }
+    tokens
+}
+
+impl GameRecord {
+    pub fn from_game(game: &StandardGame) -> Self {
+        GameRecord {
+            tags: vec![Tag {
+                name: RESULT_TAG.to_string(),
+                value: result_mark(game.result(&ResultConfig::default())).to_string(),
+            }],
+            start: game.initial_position().to_string(),
+            actions: game.actions().cloned().collect(),
+        }
+    }
+
+    /// Value of the tag with the given name, if there is one
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|tag| tag.name == name)
+            .map(|tag| tag.value.as_str())
+    }
+
+    /// Set the value of a tag, keeping its place if it is already there
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|tag| tag.name == name) {
+            Some(tag) => tag.value = value.to_string(),
+            None => self.tags.push(Tag {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    /// Replay the record, failing on the first action that is not legal
+    pub fn to_game(&self) -> Result<StandardGame, Error> {
+        let mut game: StandardGame = Game::from_fen(&self.start)?;
+        for action in &self.actions {
+            game.apply(action.clone())?;
+        }
+        Ok(game)
+    }
+
+    /// Write the record with its tags first, then one numbered pair
+    /// of actions per line, then the result
+    pub fn write(&self) -> String {
+        let mut text = String::new();
+        for tag in &self.tags {
+            text += &format!("[{} \"{}\"]\n", tag.name, escape(&tag.value));
+        }
+        text += &format!("[{} \"{}\"]\n\n", FEN_TAG, escape(&self.start));
+
+        let mut entries: Vec<String> = self.actions.iter().map(|a| a.to_string()).collect();
+        let black_first = Position::<LeiserChessGrid>::from_str(&self.start)
+            .is_ok_and(|position| position.player == Color::Black);
+        if black_first && !entries.is_empty() {
+            entries.insert(0, PLACEHOLDER.to_string());
+        }
+        for (index, pair) in entries.chunks(2).enumerate() {
+            text += &format!("{}. {}\n", index + 1, pair.join(" "));
+        }
+        text += self.tag(RESULT_TAG).unwrap_or("*");
+        text.push('\n');
+        text
+    }
+
+    /// Read a record written by GameRecord::write or by hand,
+    /// replaying every action to make sure it is legal
+    pub fn read(text: &str) -> Result<GameRecord, RecordError> {
+        let mut tags = Vec::new();
+        let mut start = None;
+        let mut lines = text.lines().enumerate().peekable();
+
+        while let Some((index, line)) = lines.peek() {
+            let (number, line) = (index + 1, *line);
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
+                lines.next();
+                continue;
+            }
+            if !trimmed.starts_with('[') {
+                break;
+            }
+            let indent_length = line.len() - trimmed.len();
+            let indent = column_of(line, indent_length);
+            let captures = TAG_PATTERN.captures(trimmed).ok_or_else(|| {
+                RecordError::new(RecordErrorKind::MalformedTag, number, indent + 1)
+            })?;
+            let value = captures.get(2).unwrap();
+            if &captures[1] == FEN_TAG {
+                // Escapes are never needed in a FEN, so positions in it match the line
+                let column = column_of(line, indent_length + value.start()) + 1;
+                let fen = unescape(value.as_str());
+                Position::<LeiserChessGrid>::from_str(&fen).map_err(|e| {
+                    RecordError::new(
+                        RecordErrorKind::InvalidPosition(e.kind),
+                        number,
+                        column + e.position,
+                    )
+                })?;
+                start = Some(fen);
+            } else {
+                tags.push(Tag {
+                    name: captures[1].to_string(),
+                    value: unescape(value.as_str()),
+                });
+            }
+            lines.next();
+        }
+
+        let mut record = GameRecord {
+            tags,
+            start: start.unwrap_or_else(|| crate::constants::STARTING_POSITION.to_string()),
+            actions: Vec::new(),
+        };
+        let tokens = lines.flat_map(|(index, line)| {
+            tokens_with_columns(line)
+                .into_iter()
+                .map(move |(token, column)| (token, index + 1, column))
+        });
+        record.read_actions(tokens)?;
+        Ok(record)
+    }
+
+    fn read_actions<'a>(
+        &mut self,
+        tokens: impl Iterator<Item = (&'a str, usize, usize)>,
+    ) -> Result<(), RecordError> {
+        let mut game: StandardGame =
+            Game::from_fen(&self.start).expect("Starting position was checked");
+        let black_first = game.current_player() == Color::Black;
+        // Number of entries in the move list so far, including the placeholder
+        let mut entries = 0;
+        let mut numbered = false;
+        let mut finished = false;
+
+        for (token, line, column) in tokens {
+            let error = |kind| Err(RecordError::new(kind, line, column));
+            if finished {
+                return error(RecordErrorKind::UnexpectedToken(token.to_string()));
+            }
+
+            let expected_number = entries / 2 + 1;
+            if let Some(number) = token.strip_suffix('.').filter(|_| token != PLACEHOLDER) {
+                if numbered || entries % 2 == 1 || number != expected_number.to_string() {
+                    return error(RecordErrorKind::UnexpectedMoveNumber(token.to_string()));
+                }
+                numbered = true;
+            } else if RESULTS.contains(&token) {
+                if self.tag(RESULT_TAG).is_none() {
+                    self.set_tag(RESULT_TAG, token);
+                }
+                finished = true;
+            } else if entries % 2 == 0 && !numbered {
+                return error(RecordErrorKind::ExpectedMoveNumber(expected_number));
+            } else if token == PLACEHOLDER {
+                if entries != 0 || !black_first {
+                    return error(RecordErrorKind::UnexpectedToken(token.to_string()));
+                }
+                entries += 1;
+                numbered = false;
+            } else if entries == 0 && black_first {
+                return error(RecordErrorKind::ExpectedPlaceholder);
+            } else {
+                let action = StandardAction::from_str(token).map_err(|e| {
+                    RecordError::new(RecordErrorKind::InvalidAction(e.kind), line, column)
+                })?;
+                game.apply(action.clone()).map_err(|error| {
+                    RecordError::new(
+                        RecordErrorKind::IllegalAction {
+                            action: token.to_string(),
+                            error,
+                        },
+                        line,
+                        column,
+                    )
+                })?;
+                self.actions.push(action);
+                entries += 1;
+                numbered = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod record_tests {
+    use super::*;
+    use crate::constants::STARTING_POSITION;
+
+    #[test]
+    pub fn replays_games() {
+        let mut game: StandardGame = Game::from_fen(STARTING_POSITION).unwrap();
+        for notation in ["a7b6", "a2b3", "h8L"] {
+            game.apply(StandardAction::from_str(notation).unwrap())
+                .unwrap();
+        }
+        let record = GameRecord::from_game(&game);
+        assert_eq!(record.start, STARTING_POSITION);
+        assert_eq!(record.actions.len(), 3);
+
+        let replayed = record.to_game().unwrap();
+        assert_eq!(replayed.to_fen(), game.to_fen());
+        assert_eq!(replayed.zobrist(), game.zobrist());
+
+        let mut illegal = record.clone();
+        illegal
+            .actions
+            .push(StandardAction::from_str("a8a8").unwrap());
+        assert!(matches!(illegal.to_game(), Err(Error::WrongPlayer(_))));
+    }
+
+    #[test]
+    pub fn writes_and_reads_records() {
+        let mut game: StandardGame = Game::from_fen(STARTING_POSITION).unwrap();
+        for notation in ["a7b6", "a2b3", "h8L"] {
+            game.apply(StandardAction::from_str(notation).unwrap())
+                .unwrap();
+        }
+        let mut record = GameRecord::from_game(&game);
+        record.set_tag("Event", "Club \"blitz\" night");
+        record.set_tag("TimeControl", "300+2");
+
+        let text = record.write();
+        assert_eq!(
+            text,
+            format!(
+                "[Result \"*\"]\n\
+                 [Event \"Club \\\"blitz\\\" night\"]\n\
+                 [TimeControl \"300+2\"]\n\
+                 [FEN \"{}\"]\n\
+                 \n\
+                 1. a7b6 a2b3\n\
+                 2. h8L\n\
+                 *\n",
+                STARTING_POSITION
+            )
+        );
+        let read = GameRecord::read(&text).unwrap();
+        assert_eq!(read, record);
+        assert_eq!(read.tag("Event"), Some("Club \"blitz\" night"));
+    }
+
+    #[test]
+    pub fn black_moves_first_after_placeholder() {
+        let fen = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS B";
+        let text = format!("[FEN \"{}\"]\n\n1. ... a2b3\n2. a7b6\n", fen);
+        let record = GameRecord::read(&text).unwrap();
+        assert_eq!(record.actions.len(), 2);
+        assert!(record.write().ends_with("1. ... a2b3\n2. a7b6\n*\n"));
+
+        let missing = GameRecord::read(&format!("[FEN \"{}\"]\n1. a2b3\n", fen));
+        assert!(matches!(
+            missing.unwrap_err().kind,
+            RecordErrorKind::ExpectedPlaceholder
+        ));
+    }
+
+    #[test]
+    pub fn errors_report_line_and_column() {
+        let error = GameRecord::read("[Event \"x\"]\n\n1. a7b6 a2b3\n2. a7b6\n").unwrap_err();
+        assert!(matches!(error.kind, RecordErrorKind::IllegalAction { .. }));
+        assert_eq!((error.line, error.column), (4, 4));
+
+        let error = GameRecord::read("1. a7b6 a2b3 h8L\n").unwrap_err();
+        assert!(matches!(error.kind, RecordErrorKind::ExpectedMoveNumber(2)));
+        assert_eq!((error.line, error.column), (1, 14));
+
+        let error = GameRecord::read("1. a7b6\n3. a2b3\n").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RecordErrorKind::UnexpectedMoveNumber(_)
+        ));
+
+        let error = GameRecord::read("1. a7z9\n").unwrap_err();
+        assert!(matches!(error.kind, RecordErrorKind::InvalidAction(_)));
+        assert_eq!((error.line, error.column), (1, 4));
+
+        // Columns count characters rather than bytes
+        let error = GameRecord::read("1.\u{a0}a7z9\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 4));
+
+        let error = GameRecord::read("[Event \"x\"\n").unwrap_err();
+        assert!(matches!(error.kind, RecordErrorKind::MalformedTag));
+        let error = GameRecord::read("\u{a0}\u{a0}[Event x]\n").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 3));
+
+        let error = GameRecord::read("[FEN \"nn6nn/8/8 W\"]\n").unwrap_err();
+        assert!(matches!(
+            error.kind,
+            RecordErrorKind::InvalidPosition(ParseErrorKind::RowCount { .. })
+        ));
+        assert_eq!(error.line, 1);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::board::Color;
+use crate::constants::*;
+
+/// Why a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// Every monarch of the losing player was zapped
+    MonarchsZapped,
+    /// The last monarchs of both players were zapped on the same turn
+    MutualDestruction,
+    /// The same board occurred too many times with the same player to move
+    Repetition,
+    /// The game went on for too many actions
+    MoveLimit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    InProgress,
+    Win { winner: Color, reason: Reason },
+    Draw { reason: Reason },
+}
+
+impl GameResult {
+    pub fn is_over(&self) -> bool {
+        !matches!(self, GameResult::InProgress)
+    }
+}
+
+/// Conditions under which a game is declared drawn
+#[derive(Clone, Copy, Debug)]
+pub struct ResultConfig {
+    /// Number of actions after which the game is a draw. Games never
+    /// hold more than MAX_MOVES actions, so larger limits act as MAX_MOVES
+    pub move_limit: usize,
+    /// Number of times a position may occur before the game is a draw
+    pub repetition_limit: usize,
+}
+
+impl Default for ResultConfig {
+    fn default() -> Self {
+        ResultConfig {
+            move_limit: MAX_MOVES,
+            repetition_limit: 3,
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::board::*;
+use crate::rules::RuleSet;
+
+/// A laser fired by a monarch in the direction it is facing
+#[derive(Clone, Copy, Debug)]
+pub struct Laser {
+    source: GridLocation,
+    direction: Orthogonal,
+}
+
+/// The route a laser takes across a board
+#[derive(Clone, Debug)]
+pub struct LaserPath {
+    /// Every square the laser passes through in order,
+    /// starting with the square of the monarch that fired it
+    pub squares: Vec<GridLocation>,
+    /// The square of the piece that absorbs the laser,
+    /// or None if the laser flies off the board
+    pub target: Option<GridLocation>,
+}
+
+impl Laser {
+    pub fn new(source: GridLocation, direction: Orthogonal) -> Self {
+        Laser { source, direction }
+    }
+
+    pub fn source(&self) -> GridLocation {
+        self.source
+    }
+
+    pub fn direction(&self) -> Orthogonal {
+        self.direction
+    }
+
+    /// Travel and reflect off pawns until the laser hits
+    /// a piece it cannot reflect off or the edge of the board
+    pub fn path_on<const N: usize>(&self, board: &Grid<N>) -> LaserPath {
+        let mut squares = vec![self.source];
+        let mut direction = self.direction;
+        let mut current = self.source;
+
+        while let Some(next) = current.step(direction, N) {
+            squares.push(next);
+            current = next;
+
+            if let Some(piece) = board.get_unchecked(&next) {
+                match piece.reflect(direction) {
+                    Some(reflection) => direction = reflection,
+                    None => {
+                        return LaserPath {
+                            squares,
+                            target: Some(next),
+                        }
+                    }
+                }
+            }
+        }
+
+        LaserPath {
+            squares,
+            target: None,
+        }
+    }
+
+    /// Fire the laser, removing the piece it hits from the board.
+    /// Returns the zapped piece and where it stood, if any
+    pub fn fire_on<const N: usize>(
+        &self,
+        board: &mut Grid<N>,
+    ) -> Option<(GridLocation, StandardPiece)> {
+        let target = self.path_on(board).target?;
+        let piece = board.get_unchecked(&target)?;
+        board.remove_unchecked(&target);
+        Some((target, piece))
+    }
+}
+
+/// The lasers of every monarch of the given color, in FEN order
+pub fn lasers<const N: usize>(board: &Grid<N>, color: Color) -> Vec<Laser> {
+    board
+        .pieces()
+        .filter_map(|(location, piece)| match (piece.kind, piece.direction) {
+            (Kind::Monarch, Direction::Orthogonal(direction)) if piece.color == color => {
+                Some(Laser::new(location, direction))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Fire the lasers of every monarch of the given color one after another,
+/// so that a later laser travels over the board left by the earlier ones.
+/// Returns the zapped pieces in the order they were removed
+pub fn fire_lasers<const N: usize>(
+    board: &mut Grid<N>,
+    color: Color,
+    rules: &RuleSet,
+) -> Vec<(GridLocation, StandardPiece)> {
+    let mut zapped = Vec::new();
+    for laser in lasers(board, color) {
+        if !rules.friendly_fire {
+            // Pieces of the firing player absorb the laser unharmed
+            let target = laser.path_on(board).target;
+            let hit = target.and_then(|target| board.get_unchecked(&target));
+            if hit.is_some_and(|piece| piece.color == color) {
+                continue;
+            }
+        }
+        zapped.extend(laser.fire_on(board));
+    }
+    zapped
+}
+
+#[cfg(test)]
+pub mod laser_tests {
+    use super::*;
+
+    #[test]
+    pub fn opening_lasers_leave_the_board() {
+        let opening_position = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS";
+        let mut grid = LeiserChessGrid::from_str(opening_position).unwrap();
+        assert!(fire_lasers(&mut grid, Color::White, &RuleSet::default()).is_empty());
+        assert!(fire_lasers(&mut grid, Color::Black, &RuleSet::default()).is_empty());
+        assert_eq!(grid.to_string(), opening_position);
+    }
+
+    #[test]
+    pub fn laser_reflects_off_pawn_mirrors() {
+        // The white monarch on a8 fires north into the SE pawn on a1,
+        // which turns it east into the back of the black pawn on d1
+        let grid = LeiserChessGrid::from_str("SE2ne4/8/8/8/8/8/8/NN7").unwrap();
+        let path = lasers(&grid, Color::White)[0].path_on(&grid);
+        assert_eq!(path.squares.len(), 11);
+        assert_eq!(path.squares[7].to_string(), "a1");
+        assert_eq!(path.target.map(|t| t.to_string()), Some("d1".to_string()));
+    }
+
+    #[test]
+    pub fn laser_zaps_monarchs() {
+        let mut grid = LeiserChessGrid::from_str("ss7/8/8/8/8/8/8/NN7").unwrap();
+        let zapped = fire_lasers(&mut grid, Color::White, &RuleSet::default());
+        assert_eq!(zapped.len(), 1);
+        assert_eq!(zapped[0].0.to_string(), "a1");
+        assert_eq!(grid.to_string(), "8/8/8/8/8/8/8/NN7");
+    }
+
+    #[test]
+    pub fn lasers_spare_own_pieces_without_friendly_fire() {
+        // The laser of the monarch on a8 is absorbed by its own monarch on a1
+        let position = "NN7/8/8/8/8/8/8/NN7";
+        let mut grid = LeiserChessGrid::from_str(position).unwrap();
+        assert!(fire_lasers(&mut grid, Color::White, &RuleSet::RELAXED).is_empty());
+        assert_eq!(grid.to_string(), position);
+        assert_eq!(
+            fire_lasers(&mut grid, Color::White, &RuleSet::default()).len(),
+            1
+        );
+
+        let mut grid = LeiserChessGrid::from_str("ss7/8/8/8/8/8/8/NN7").unwrap();
+        assert_eq!(
+            fire_lasers(&mut grid, Color::White, &RuleSet::RELAXED).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,18 @@
+pub mod action;
+pub mod board;
+pub mod constants;
+pub mod eval;
+pub mod game;
+pub mod laser;
+pub mod parser;
+pub mod perft;
+pub mod protocol;
+pub mod rules;
+pub mod search;
+#[cfg(feature = "serde")]
+pub mod serialization;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod zobrist;
@@ -1,9 +1,121 @@
-mod action;
-mod board;
-mod constants;
-mod game;
-mod parser;
+use backend::board::Parseable;
+use backend::constants::{MAX_BOARD_SIZE, STARTING_POSITION};
+use backend::game::GridGame;
+use backend::perft::{divide, perft};
+use backend::protocol::Engine;
+use backend::search::{SearchLimits, Searcher};
+use std::env;
+use std::io::{self, BufRead};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: backend [(perft | divide | search) <depth> [fen]]";
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        return run_protocol();
+    }
+    let (command, depth) = match (args.first(), args.get(1).map(|d| d.parse::<usize>())) {
+        (Some(command), Some(Ok(depth))) => (command.as_str(), depth),
+        _ => exit_with(USAGE),
+    };
+    let fen = match args[2..].join(" ") {
+        fen if fen.is_empty() => STARTING_POSITION.to_string(),
+        fen => fen,
+    };
+
+    // Other board sizes are recognised by their number of rows
+    let rows = fen
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .count();
+    match rows {
+        2 => run::<2>(command, depth, &fen),
+        3 => run::<3>(command, depth, &fen),
+        4 => run::<4>(command, depth, &fen),
+        5 => run::<5>(command, depth, &fen),
+        6 => run::<6>(command, depth, &fen),
+        7 => run::<7>(command, depth, &fen),
+        8 => run::<8>(command, depth, &fen),
+        9 => run::<9>(command, depth, &fen),
+        10 => run::<10>(command, depth, &fen),
+        11 => run::<11>(command, depth, &fen),
+        12 => run::<12>(command, depth, &fen),
+        13 => run::<13>(command, depth, &fen),
+        14 => run::<14>(command, depth, &fen),
+        15 => run::<15>(command, depth, &fen),
+        16 => run::<16>(command, depth, &fen),
+        _ => exit_with(&format!(
+            "Boards must have between 2 and {} rows, not {}",
+            MAX_BOARD_SIZE, rows
+        )),
+    }
+}
+
+/// Run a perft, divide or search command on a board of size N
+fn run<const N: usize>(command: &str, depth: usize, fen: &str) {
+    let mut game = GridGame::<N>::from_fen(fen).unwrap_or_else(|e| exit_with(&e.to_string()));
+
+    let start = Instant::now();
+    match command {
+        "perft" => println!("{}", perft(&mut game, depth)),
+        "divide" => {
+            for (action, counts) in divide(&mut game, depth) {
+                println!("{} {}", action.to_string(), counts);
+            }
+        }
+        "search" => {
+            let limits = SearchLimits {
+                depth: Some(depth),
+                ..SearchLimits::default()
+            };
+            let result = Searcher::new(limits).search_with(&mut game, |iteration| {
+                let pv: Vec<String> = iteration.pv.iter().map(|a| a.to_string()).collect();
+                println!(
+                    "depth {} score {} nodes {} pv {}",
+                    iteration.depth,
+                    iteration.score,
+                    iteration.nodes,
+                    pv.join(" ")
+                );
+            });
+            match result.best_action {
+                Some(action) => println!("bestmove {}", action.to_string()),
+                None => println!("bestmove none"),
+            }
+        }
+        _ => exit_with(USAGE),
+    }
+    eprintln!("took {:.3}s", start.elapsed().as_secs_f64());
+}
+
+/// Speak the engine protocol over stdin and stdout until told to quit
+fn run_protocol() {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let printer = thread::spawn(move || {
+        for line in receiver {
+            println!("{}", line);
+        }
+    });
+
+    let mut engine = Engine::new(sender);
+    for line in io::stdin().lock().lines() {
+        match line {
+            Ok(line) if engine.handle(&line) => {}
+            _ => break,
+        }
+    }
+    // Closing the channel lets the printer finish
+    drop(engine);
+    printer.join().expect("Printer thread panicked");
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
 }
@@ -1,29 +1,78 @@
 use regex::Regex;
+use std::sync::LazyLock;
+use thiserror::Error;
 
-/// Accepts the number out of a string and returns a
-/// tuple with the number and the remaining string
-/// if the first character is not a number, it returns None
-pub fn parse_number<'a>(input: &'a str) -> (Option<usize>, &'a str) {
-    if input.is_empty() {
-        return (None, input);
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s+").unwrap());
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    #[error("Unexpected character '{0}'")]
+    UnexpectedCharacter(char),
+    #[error("Unexpected end of input")]
+    UnexpectedEnd,
+    #[error("Invalid piece '{0}'")]
+    InvalidPiece(String),
+    #[error("Invalid location '{0}'")]
+    InvalidLocation(String),
+    #[error("Invalid player '{0}'")]
+    InvalidPlayer(String),
+    #[error("Invalid rotation '{0}'")]
+    InvalidRotation(String),
+    #[error("Invalid action '{0}'")]
+    InvalidAction(String),
+    #[error("Row {row} has {length} squares instead of {expected}")]
+    RowLength {
+        row: usize,
+        length: usize,
+        expected: usize,
+    },
+    #[error("Board has {count} rows instead of {expected}")]
+    RowCount { count: usize, expected: usize },
+}
+
+/// Reason a string could not be parsed, along with the
+/// byte offset into the input where the problem was found
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind} at position {position}")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: usize,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, position: usize) -> Self {
+        ParseError { kind, position }
     }
-    let c = input.chars().nth(0).unwrap();
-    if !c.is_numeric() {
-        return (None, input);
+
+    /// Shift the position of an error found in a substring
+    /// that started at the given offset of the full input
+    pub fn offset(self, offset: usize) -> Self {
+        ParseError {
+            kind: self.kind,
+            position: self.position + offset,
+        }
     }
+}
 
-    let number = c.to_digit(10).unwrap();
-    (Some(number as usize), &input[1..])
+/// Accepts the number out of a string, which may have several digits,
+/// and returns a tuple with the number and the remaining string
+/// if the first character is not a number, it returns None
+pub fn parse_number(input: &str) -> (Option<usize>, &str) {
+    let digits = input.len() - input.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    match input[..digits].parse() {
+        Ok(number) => (Some(number), &input[digits..]),
+        Err(_) => (None, input),
+    }
 }
 
 /// Accepts a string with "/" as the first character
 /// and returns a tuple with "/" and the remaining string
 /// if the first character is not "/", it returns None
-pub fn parse_row<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
+pub fn parse_row(input: &str) -> (Option<&str>, &str) {
     if input.is_empty() {
         return (None, input);
     }
-    let c = input.chars().nth(0).unwrap();
+    let c = input.chars().next().unwrap();
     if c == '/' {
         return (Some("/"), &input[1..]);
     }
@@ -33,9 +82,8 @@ pub fn parse_row<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
 /// Accepts a whitespace led string and returns a tuple
 /// with the whitespace and the remaining string
 /// if the first character is not whitespace, it returns None
-pub fn parse_whitespace<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
-    Regex::new(r"^\s+")
-        .unwrap()
+pub fn parse_whitespace(input: &str) -> (Option<&str>, &str) {
+    WHITESPACE
         .find(input)
         .map(|m| (Some(m.as_str()), &input[m.end()..]))
         .unwrap_or((None, input))
@@ -44,11 +92,11 @@ pub fn parse_whitespace<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
 /// Accepts a string leading with W,B,w or b and returns a tuple
 /// with the first string and the remaining string
 /// if the first character is not expected return None
-pub fn parse_current_player<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
+pub fn parse_current_player(input: &str) -> (Option<&str>, &str) {
     if input.is_empty() {
         return (None, input);
     }
-    let c = input.chars().nth(0).unwrap();
+    let c = input.chars().next().unwrap();
     match c {
         'W' => (Some(&input[0..1]), &input[1..]),
         'B' => (Some(&input[0..1]), &input[1..]),
@@ -60,11 +108,11 @@ pub fn parse_current_player<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
 
 /// Accepts a string leading with a piece descriptor (such as NN, WW, etc..)
 /// and returns a tuple with the piece descriptor and the remaining string
-pub fn parse_piece_fen<'a>(input: &'a str) -> (Option<&'a str>, &'a str) {
-    if input.len() < 2 {
+pub fn parse_piece_fen(input: &str) -> (Option<&str>, &str) {
+    // The first two bytes may not be whole characters
+    let Some(test) = input.get(0..2) else {
         return (None, input);
-    }
-    let test = &input[0..2];
+    };
     match test {
         "NN" => (Some("NN"), &input[2..]),
         "WW" => (Some("WW"), &input[2..]),
@@ -0,0 +1,297 @@
+use crate::action::standard::*;
+use crate::board::*;
+use crate::game::*;
+use std::fmt;
+use std::ops::AddAssign;
+
+/// Number of leaf positions reached by perft, broken down by
+/// the kind of action that led to each leaf
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerftCounts {
+    pub nodes: u64,
+    pub moves: u64,
+    pub shoves: u64,
+    pub rotations: u64,
+    pub nulls: u64,
+    /// Leaves reached by an action after which a laser zapped at least one piece
+    pub zaps: u64,
+}
+
+impl AddAssign for PerftCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.moves += other.moves;
+        self.shoves += other.shoves;
+        self.rotations += other.rotations;
+        self.nulls += other.nulls;
+        self.zaps += other.zaps;
+    }
+}
+
+impl fmt::Display for PerftCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "nodes {} moves {} shoves {} rotations {} nulls {} zaps {}",
+            self.nodes, self.moves, self.shoves, self.rotations, self.nulls, self.zaps
+        )
+    }
+}
+
+/// True once either player has lost all of their monarchs,
+/// after which no more actions are taken
+fn is_over<const N: usize>(game: &GridGame<N>) -> bool {
+    [Color::White, Color::Black]
+        .into_iter()
+        .any(|color| game.board().monarch_count(color) == 0)
+}
+
+/// Count the positions reached after exactly depth legal actions.
+///
+/// Positions where a player has no monarchs left end the game and
+/// contribute no leaves beyond their own depth
+pub fn perft<const N: usize>(game: &mut GridGame<N>, depth: usize) -> PerftCounts {
+    if depth == 0 {
+        return PerftCounts {
+            nodes: 1,
+            ..PerftCounts::default()
+        };
+    }
+    if is_over(game) {
+        return PerftCounts::default();
+    }
+
+    let mut counts = PerftCounts::default();
+    for action in game.legal_actions() {
+        counts += perft_action(game, action, depth);
+    }
+    counts
+}
+
+/// Count the positions reached after exactly depth legal actions,
+/// separately for each legal action from the current position
+pub fn divide<const N: usize>(
+    game: &mut GridGame<N>,
+    depth: usize,
+) -> Vec<(StandardAction, PerftCounts)> {
+    if depth == 0 || is_over(game) {
+        return Vec::new();
+    }
+    game.legal_actions()
+        .into_iter()
+        .map(|action| (action.clone(), perft_action(game, action, depth)))
+        .collect()
+}
+
+/// Apply the action, count the leaves below it and take it back again
+fn perft_action<const N: usize>(
+    game: &mut GridGame<N>,
+    action: StandardAction,
+    depth: usize,
+) -> PerftCounts {
+    let kind = action.kind(game.board());
+    game.apply(action).expect("Legal action was rejected");
+
+    let counts = if depth == 1 {
+        PerftCounts {
+            nodes: 1,
+            moves: (kind == ActionKind::Move) as u64,
+            shoves: (kind == ActionKind::Shove) as u64,
+            rotations: (kind == ActionKind::Rotation) as u64,
+            nulls: (kind == ActionKind::Null) as u64,
+            zaps: !game.last_zapped().is_empty() as u64,
+        }
+    } else {
+        perft(game, depth - 1)
+    };
+
+    game.undo().expect("Applied action cannot be undone");
+    counts
+}
+
+#[cfg(test)]
+pub mod perft_tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::constants::STARTING_POSITION;
+
+    fn opening() -> StandardGame {
+        Game::from_fen(STARTING_POSITION).unwrap()
+    }
+
+    /// Count the leaves without move generation, by trying every source with
+    /// every destination and rotation and keeping whatever passes
+    /// StandardAction::validate and is accepted by the game
+    fn brute_force_perft<const N: usize>(game: &mut GridGame<N>, depth: usize) -> PerftCounts {
+        if depth == 0 {
+            return PerftCounts {
+                nodes: 1,
+                ..PerftCounts::default()
+            };
+        }
+        if is_over(game) {
+            return PerftCounts::default();
+        }
+
+        let squares: Vec<GridLocation> = (0..N)
+            .flat_map(|y| (0..N).map(move |x| GridLocation { x, y }))
+            .collect();
+        let mut candidates = Vec::new();
+        for &source in &squares {
+            for &destination in &squares {
+                candidates.push(StandardAction::movement(source, destination));
+            }
+            for rotation in [Rotation::Right, Rotation::Left, Rotation::UTurn] {
+                candidates.push(StandardAction::rotation(source, rotation));
+            }
+        }
+
+        let mut counts = PerftCounts::default();
+        for action in candidates {
+            if action.validate(game.board(), game.rules()).is_err() {
+                continue;
+            }
+            let rotation = action.relative_rotation().is_some();
+            let null = !rotation && action.source() == action.destination();
+            let shove =
+                !rotation && !null && game.board().get_unchecked(&action.destination()).is_some();
+            if game.apply(action).is_err() {
+                continue;
+            }
+            counts += if depth == 1 {
+                PerftCounts {
+                    nodes: 1,
+                    moves: (!rotation && !null && !shove) as u64,
+                    shoves: shove as u64,
+                    rotations: rotation as u64,
+                    nulls: null as u64,
+                    zaps: !game.last_zapped().is_empty() as u64,
+                }
+            } else {
+                brute_force_perft(game, depth - 1)
+            };
+            game.undo().unwrap();
+        }
+        counts
+    }
+
+    #[test]
+    pub fn opening_counts() {
+        let mut game = opening();
+        assert_eq!(
+            perft(&mut game, 0),
+            PerftCounts {
+                nodes: 1,
+                ..PerftCounts::default()
+            }
+        );
+        assert_eq!(
+            perft(&mut game, 1),
+            PerftCounts {
+                nodes: 64,
+                moves: 34,
+                shoves: 6,
+                rotations: 24,
+                nulls: 0,
+                zaps: 4,
+            }
+        );
+        assert_eq!(game.to_fen(), STARTING_POSITION);
+    }
+
+    #[test]
+    pub fn opening_golden_counts() {
+        // Every count comes from brute_force_perft rather than move
+        // generation, and perft has to agree with it. The split by kind at
+        // depth 1 can also be checked by hand: 8 pieces turning 3 ways each,
+        // 34 steps onto empty squares and 6 shoves
+        let mut game = opening();
+        let reference = brute_force_perft(&mut game, 1);
+        assert_eq!(reference.nodes, 64);
+        assert_eq!(perft(&mut game, 1), reference);
+
+        let reference = brute_force_perft(&mut game, 2);
+        assert_eq!(perft(&mut game, 2), reference);
+        assert_eq!(
+            reference,
+            PerftCounts {
+                nodes: 4096,
+                moves: 2176,
+                shoves: 384,
+                rotations: 1536,
+                nulls: 0,
+                zaps: 256,
+            }
+        );
+        let reference = brute_force_perft(&mut game, 3);
+        assert_eq!(perft(&mut game, 3), reference);
+        assert_eq!(
+            reference,
+            PerftCounts {
+                nodes: 260656,
+                moves: 139776,
+                shoves: 23680,
+                rotations: 97152,
+                nulls: 48,
+                zaps: 19092,
+            }
+        );
+    }
+
+    #[test]
+    pub fn generation_matches_brute_force() {
+        for fen in [
+            "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS W",
+            "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS B",
+            "ss7/8/2nw5/8/5SE2/8/8/SS6NN B",
+        ] {
+            let mut game: StandardGame = Game::from_fen(fen).unwrap();
+            for depth in 1..=2 {
+                assert_eq!(
+                    perft(&mut game, depth),
+                    brute_force_perft(&mut game, depth),
+                    "{} at depth {}",
+                    fen,
+                    depth
+                );
+            }
+        }
+        let mut game: GridGame<4> = Game::from_fen("nn3/1se2/2NW1/3SS W").unwrap();
+        assert_eq!(perft(&mut game, 3), brute_force_perft(&mut game, 3));
+    }
+
+    #[test]
+    pub fn divide_sums_to_perft() {
+        let mut game = opening();
+        let divided = divide(&mut game, 2);
+        assert_eq!(divided.len(), 64);
+
+        let mut total = PerftCounts::default();
+        for (_, counts) in divided {
+            total += counts;
+        }
+        assert_eq!(total, perft(&mut game, 2));
+        assert_eq!(game.history_length(), 0);
+    }
+
+    #[test]
+    pub fn counts_small_boards() {
+        // The lone white monarch can step to three squares or turn three ways
+        let mut game: GridGame<4> = Game::from_fen("nn3/4/4/3SS W").unwrap();
+        assert_eq!(
+            perft(&mut game, 1),
+            PerftCounts {
+                nodes: 6,
+                moves: 3,
+                rotations: 3,
+                ..PerftCounts::default()
+            }
+        );
+
+        let mut total = PerftCounts::default();
+        for (_, counts) in divide(&mut game, 3) {
+            total += counts;
+        }
+        assert_eq!(total, perft(&mut game, 3));
+    }
+}
@@ -0,0 +1,518 @@
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use crate::constants::*;
+use crate::game::{self, StandardGame};
+use crate::rules::RuleSet;
+use crate::search::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+
+const ENGINE_NAME: &str = "LeiserChess";
+const ENGINE_AUTHOR: &str = "LeiserChess developers";
+const MAX_TABLE_SIZE_MB: usize = 4096;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Unknown command {0}")]
+    UnknownCommand(String),
+    #[error("Missing {0}")]
+    MissingArgument(&'static str),
+    #[error("Invalid number {0}")]
+    InvalidNumber(String),
+    #[error("Unknown option {0}")]
+    UnknownOption(String),
+    #[error("Unknown rules {0}")]
+    UnknownRules(String),
+    #[error("Invalid position: {0}")]
+    InvalidPosition(#[from] ParseError),
+    #[error("Illegal action: {0}")]
+    IllegalAction(#[from] game::Error),
+}
+
+/// Limits given to a go command
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GoOptions {
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// Time left on the clocks of white and black
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    /// Time added to the clocks of white and black after every action
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    /// Search until told to stop
+    pub infinite: bool,
+}
+
+impl GoOptions {
+    /// The clock of the given player, if the GUI told us about it
+    pub fn clock(&self, player: Color) -> Option<Clock> {
+        let (remaining, increment) = match player {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        Some(Clock {
+            remaining: remaining?,
+            increment: increment.unwrap_or(Duration::ZERO),
+            moves_to_go: self.movestogo,
+        })
+    }
+}
+
+/// A line sent to the engine by a GUI or match runner,
+/// in the style of the Universal Chess Interface
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Uci,
+    IsReady,
+    NewGame,
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
+    /// Start from a FEN, such as those written by the frontend's
+    /// GameState.toFEN, then take the given actions
+    Position {
+        fen: String,
+        actions: Vec<String>,
+    },
+    Go(GoOptions),
+    Stop,
+    Quit,
+}
+
+fn parse_millis(token: Option<&str>, name: &'static str) -> Result<Duration, Error> {
+    // Clocks may run into negative time when a player is about to flag
+    let millis: i64 = parse_number(token, name)?;
+    Ok(Duration::from_millis(millis.max(0) as u64))
+}
+
+fn parse_number<T: std::str::FromStr>(token: Option<&str>, name: &'static str) -> Result<T, Error> {
+    let token = token.ok_or(Error::MissingArgument(name))?;
+    token
+        .parse()
+        .map_err(|_| Error::InvalidNumber(token.to_string()))
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, Error> {
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next().ok_or(Error::MissingArgument("command"))?;
+        match command {
+            "uci" => Ok(Command::Uci),
+            "isready" => Ok(Command::IsReady),
+            "ucinewgame" => Ok(Command::NewGame),
+            "stop" => Ok(Command::Stop),
+            "quit" => Ok(Command::Quit),
+            "setoption" => Self::parse_option(tokens),
+            "position" => Self::parse_position(tokens),
+            "go" => Self::parse_go(tokens),
+            _ => Err(Error::UnknownCommand(command.to_string())),
+        }
+    }
+
+    /// setoption name <name> [value <value>], where both may contain spaces
+    fn parse_option<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Command, Error> {
+        if tokens.next() != Some("name") {
+            return Err(Error::MissingArgument("option name"));
+        }
+        let rest: Vec<&str> = tokens.collect();
+        let (name, value) = match rest.iter().position(|&token| token == "value") {
+            Some(index) => (&rest[..index], Some(rest[index + 1..].join(" "))),
+            None => (&rest[..], None),
+        };
+        if name.is_empty() {
+            return Err(Error::MissingArgument("option name"));
+        }
+        Ok(Command::SetOption {
+            name: name.join(" "),
+            value,
+        })
+    }
+
+    /// position (startpos | fen <fen>) [moves <action>...]
+    fn parse_position<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Command, Error> {
+        let fen = match tokens.next() {
+            Some("startpos") => STARTING_POSITION.to_string(),
+            Some("fen") => {
+                let fen: Vec<&str> = tokens.by_ref().take_while(|&t| t != "moves").collect();
+                if fen.is_empty() {
+                    return Err(Error::MissingArgument("FEN"));
+                }
+                return Ok(Command::Position {
+                    fen: fen.join(" "),
+                    actions: tokens.map(str::to_string).collect(),
+                });
+            }
+            _ => return Err(Error::MissingArgument("startpos or fen")),
+        };
+        let actions = match tokens.next() {
+            Some("moves") => tokens.map(str::to_string).collect(),
+            _ => vec![],
+        };
+        Ok(Command::Position { fen, actions })
+    }
+
+    fn parse_go<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<Command, Error> {
+        let mut options = GoOptions::default();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => options.depth = Some(parse_number(tokens.next(), "depth")?),
+                "nodes" => options.nodes = Some(parse_number(tokens.next(), "nodes")?),
+                "movetime" => options.movetime = Some(parse_millis(tokens.next(), "movetime")?),
+                "wtime" => options.wtime = Some(parse_millis(tokens.next(), "wtime")?),
+                "btime" => options.btime = Some(parse_millis(tokens.next(), "btime")?),
+                "winc" => options.winc = Some(parse_millis(tokens.next(), "winc")?),
+                "binc" => options.binc = Some(parse_millis(tokens.next(), "binc")?),
+                "movestogo" => options.movestogo = Some(parse_number(tokens.next(), "movestogo")?),
+                "infinite" => options.infinite = true,
+                // Limits we do not know about are ignored
+                _ => {}
+            }
+        }
+        Ok(Command::Go(options))
+    }
+}
+
+/// Describe a finished search iteration as an info line
+pub fn info_line(result: &SearchResult) -> String {
+    let score = if is_win_score(result.score) {
+        // Convert the distance to a win from plies to the mover's turns
+        let plies = WIN_SCORE - result.score.abs();
+        format!("mate {}", result.score.signum() * (plies + 1) / 2)
+    } else {
+        format!("cp {}", result.score)
+    };
+    let pv: Vec<String> = result.pv.iter().map(|action| action.to_string()).collect();
+    format!(
+        "info depth {} score {} nodes {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        result.elapsed.as_millis(),
+        pv.join(" ")
+    )
+}
+
+/// A search running on another thread
+struct RunningSearch {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Engine driven by protocol commands, one line at a time.
+/// Responses are sent line by line to the output channel,
+/// including those written while searching in the background
+pub struct Engine {
+    output: Sender<String>,
+    fen: String,
+    actions: Vec<StandardAction>,
+    rules: RuleSet,
+    table: Arc<TranspositionTable>,
+    search: Option<RunningSearch>,
+}
+
+impl Engine {
+    pub fn new(output: Sender<String>) -> Self {
+        Engine {
+            output,
+            fen: STARTING_POSITION.to_string(),
+            actions: Vec::new(),
+            rules: RuleSet::default(),
+            table: Arc::new(TranspositionTable::default()),
+            search: None,
+        }
+    }
+
+    fn send(&self, line: String) {
+        // Nobody is listening once the output is closed
+        let _ = self.output.send(line);
+    }
+
+    /// Handle a single line of input, returning false once the engine should quit.
+    /// Invalid commands are reported as info strings and otherwise ignored
+    pub fn handle(&mut self, line: &str) -> bool {
+        if line.trim().is_empty() {
+            return true;
+        }
+        match Command::parse(line).and_then(|command| self.execute(command)) {
+            Ok(running) => running,
+            Err(e) => {
+                self.send(format!("info string {}", e));
+                true
+            }
+        }
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<bool, Error> {
+        match command {
+            Command::Uci => {
+                self.send(format!("id name {}", ENGINE_NAME));
+                self.send(format!("id author {}", ENGINE_AUTHOR));
+                self.send(format!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_TABLE_SIZE_MB, MAX_TABLE_SIZE_MB
+                ));
+                self.send("option name Clear Hash type button".to_string());
+                let presets: Vec<String> = RuleSet::PRESETS
+                    .iter()
+                    .map(|(name, _)| format!("var {}", name))
+                    .collect();
+                self.send(format!(
+                    "option name Rules type combo default {} {}",
+                    RuleSet::PRESETS[0].0,
+                    presets.join(" ")
+                ));
+                self.send("uciok".to_string());
+            }
+            Command::IsReady => self.send("readyok".to_string()),
+            Command::NewGame => {
+                self.stop();
+                self.table.clear();
+            }
+            Command::SetOption { name, value } => self.set_option(&name, value.as_deref())?,
+            Command::Position { fen, actions } => {
+                let actions = actions
+                    .iter()
+                    .map(|action| StandardAction::from_str(action))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Make sure the position can be reached before accepting it
+                Self::replay(&fen, &actions, self.rules)?;
+                self.fen = fen;
+                self.actions = actions;
+            }
+            Command::Go(options) => self.go(options)?,
+            Command::Stop => self.stop(),
+            Command::Quit => {
+                self.stop();
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), Error> {
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let megabytes: usize = parse_number(value, "hash size")?;
+                self.stop();
+                self.table = Arc::new(TranspositionTable::new(
+                    megabytes.clamp(1, MAX_TABLE_SIZE_MB),
+                ));
+            }
+            "clear hash" => {
+                self.stop();
+                self.table.clear();
+            }
+            "rules" => {
+                let name = value.ok_or(Error::MissingArgument("rules"))?;
+                let rules =
+                    RuleSet::preset(name).ok_or_else(|| Error::UnknownRules(name.to_string()))?;
+                self.stop();
+                // Scores found under other rules no longer hold
+                self.table.clear();
+                self.rules = rules;
+            }
+            _ => return Err(Error::UnknownOption(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// The game reached by taking the actions from the FEN under the given rules
+    fn replay(
+        fen: &str,
+        actions: &[StandardAction],
+        rules: RuleSet,
+    ) -> Result<StandardGame, Error> {
+        let position = Position::from_str(fen)?;
+        let mut game = StandardGame::with_rules(position.board, position.player, rules);
+        for action in actions {
+            game.apply(action.clone())?;
+        }
+        Ok(game)
+    }
+
+    fn go(&mut self, options: GoOptions) -> Result<(), Error> {
+        self.stop();
+        let mut game = Self::replay(&self.fen, &self.actions, self.rules)?;
+        let limits = SearchLimits {
+            depth: options.depth,
+            nodes: options.nodes,
+            time: options.movetime.filter(|_| !options.infinite),
+            clock: options
+                .clock(game.current_player())
+                .filter(|_| !options.infinite),
+        };
+
+        let mut searcher = Searcher::new(limits);
+        searcher.set_table(self.table.clone());
+        let stop = searcher.stop_handle();
+        let output = self.output.clone();
+        let handle = thread::spawn(move || {
+            let result = searcher.search_with(&mut game, |iteration| {
+                let _ = output.send(info_line(iteration));
+            });
+            let best = match result.best_action {
+                Some(action) => action.to_string(),
+                None => "(none)".to_string(),
+            };
+            let _ = output.send(format!("bestmove {}", best));
+        });
+
+        self.search = Some(RunningSearch { handle, stop });
+        Ok(())
+    }
+
+    /// Stop the running search, if any, and wait for it to report its best action
+    pub fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            search.handle.join().expect("Search thread panicked");
+        }
+    }
+
+    /// Wait for the running search, if any, to finish by itself
+    pub fn wait(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.handle.join().expect("Search thread panicked");
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+pub mod protocol_tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver};
+
+    fn engine() -> (Engine, Receiver<String>) {
+        let (sender, receiver) = mpsc::channel();
+        (Engine::new(sender), receiver)
+    }
+
+    #[test]
+    pub fn parses_commands() {
+        assert_eq!(
+            Command::parse("position fen nn6nn/8/8/8/8/8/8/SS6SS B moves a1b2 h8U").unwrap(),
+            Command::Position {
+                fen: "nn6nn/8/8/8/8/8/8/SS6SS B".to_string(),
+                actions: vec!["a1b2".to_string(), "h8U".to_string()],
+            }
+        );
+        assert_eq!(
+            Command::parse("position startpos").unwrap(),
+            Command::Position {
+                fen: STARTING_POSITION.to_string(),
+                actions: vec![],
+            }
+        );
+        assert_eq!(
+            Command::parse("go depth 4 movetime 1500 wtime 100").unwrap(),
+            Command::Go(GoOptions {
+                depth: Some(4),
+                movetime: Some(Duration::from_millis(1500)),
+                wtime: Some(Duration::from_millis(100)),
+                ..GoOptions::default()
+            })
+        );
+        let options = match Command::parse("go wtime 1000 btime -20 binc 500 movestogo 5") {
+            Ok(Command::Go(options)) => options,
+            _ => panic!("Expected a go command"),
+        };
+        assert_eq!(
+            options.clock(Color::Black),
+            Some(Clock {
+                remaining: Duration::ZERO,
+                increment: Duration::from_millis(500),
+                moves_to_go: Some(5),
+            })
+        );
+        assert_eq!(
+            options.clock(Color::White).unwrap().increment,
+            Duration::ZERO
+        );
+        assert_eq!(
+            Command::parse("setoption name Clear Hash").unwrap(),
+            Command::SetOption {
+                name: "Clear Hash".to_string(),
+                value: None,
+            }
+        );
+        assert!(matches!(
+            Command::parse("go nodes many"),
+            Err(Error::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            Command::parse("castle"),
+            Err(Error::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    pub fn handshake_and_search() {
+        let (mut engine, output) = engine();
+        assert!(engine.handle("uci"));
+        assert!(engine.handle("setoption name Hash value 1"));
+        assert!(engine.handle("isready"));
+        let lines: Vec<String> = output.try_iter().collect();
+        assert_eq!(lines.first().unwrap(), "id name LeiserChess");
+        assert_eq!(&lines[lines.len() - 2..], ["uciok", "readyok"]);
+
+        assert!(engine.handle("position startpos moves a7b6 a2b3"));
+        assert!(engine.handle("go depth 2"));
+        engine.wait();
+        let lines: Vec<String> = output.try_iter().collect();
+        assert!(lines[0].starts_with("info depth 1 score cp"));
+        assert!(lines[1].starts_with("info depth 2 "));
+        assert!(lines[2].starts_with("bestmove "));
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    pub fn stop_reports_best_action() {
+        let (mut engine, output) = engine();
+        engine.handle("position fen nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS B");
+        engine.handle("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        engine.handle("stop");
+        let lines: Vec<String> = output.try_iter().collect();
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    pub fn invalid_positions_are_reported() {
+        let (mut engine, output) = engine();
+        assert!(engine.handle("position fen nn6nn/8 W"));
+        assert!(engine.handle("position startpos moves a7a7"));
+        let lines: Vec<String> = output.try_iter().collect();
+        assert!(lines[0].starts_with("info string Invalid position"));
+        assert!(lines[1].starts_with("info string Illegal action"));
+    }
+
+    #[test]
+    pub fn rules_can_be_chosen() {
+        let (mut engine, output) = engine();
+        // Only the relaxed rules let the monarch on c3 shove the one on d4
+        let position = "position fen 8/8/2NN5/3ss4/8/8/8/8 W moves c3d4";
+        engine.handle(position);
+        engine.handle("setoption name Rules value relaxed");
+        engine.handle(position);
+        engine.handle("setoption name Rules value 1999");
+        let lines: Vec<String> = output.try_iter().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("info string Illegal action"));
+        assert_eq!(lines[1], "info string Unknown rules 1999");
+        assert_eq!(engine.actions.len(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::board::*;
+use crate::constants::*;
+use thiserror::Error;
+
+/// Why a piece may not be shoved
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShoveError {
+    #[error("Monarchs cannot be shoved")]
+    Monarch,
+    #[error("Piece is too close to the center to be shoved")]
+    TooCentral,
+}
+
+/// How far from the center a piece must be for a pawn to shove it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QiRule {
+    /// The shoved piece must be strictly further from the center
+    Further,
+    /// The shoved piece may also be as far from the center as the pawn
+    AtLeastAsFar,
+    /// Pawns may shove pieces anywhere
+    Anywhere,
+}
+
+impl QiRule {
+    /// Whether a pawn on the source may shove the piece on the target,
+    /// on a board with the given number of rows and columns
+    pub fn allows(&self, source: GridLocation, target: GridLocation, size: usize) -> bool {
+        match self {
+            QiRule::Further => target.qi(size) > source.qi(size),
+            QiRule::AtLeastAsFar => target.qi(size) >= source.qi(size),
+            QiRule::Anywhere => true,
+        }
+    }
+}
+
+/// The rules that change from one year of LeiserChess to the next.
+///
+/// Everything else, such as how pieces move and how lasers reflect
+/// off pawns, is shared by every rule set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuleSet {
+    /// Whether monarchs can be shoved like pawns
+    pub shove_monarchs: bool,
+    /// Which pieces pawns may shove, monarchs may shove any piece
+    /// that can be shoved at all
+    pub pawn_shoves: QiRule,
+    /// Whether monarchs may stay as they are instead of acting
+    pub null_moves: bool,
+    /// Whether lasers zap the pieces of the player who fired them,
+    /// otherwise those pieces absorb the laser and stay on the board
+    pub friendly_fire: bool,
+    /// Most pieces a single action may name as victims
+    pub max_victims: usize,
+}
+
+impl RuleSet {
+    /// The rules of 2023, played by default
+    pub const LEISERCHESS_2023: RuleSet = RuleSet {
+        shove_monarchs: false,
+        pawn_shoves: QiRule::Further,
+        null_moves: true,
+        friendly_fire: true,
+        max_victims: MAX_VICTIMS,
+    };
+
+    /// A looser variant in which anything may be shoved, pawns may
+    /// shove pieces level with them and lasers spare their own side
+    pub const RELAXED: RuleSet = RuleSet {
+        shove_monarchs: true,
+        pawn_shoves: QiRule::AtLeastAsFar,
+        null_moves: true,
+        friendly_fire: false,
+        max_victims: MAX_VICTIMS,
+    };
+
+    /// Every preset along with the name it is known by
+    pub const PRESETS: [(&'static str, RuleSet); 2] = [
+        ("2023", RuleSet::LEISERCHESS_2023),
+        ("relaxed", RuleSet::RELAXED),
+    ];
+
+    /// The preset with the given name, if there is one
+    pub fn preset(name: &str) -> Option<RuleSet> {
+        RuleSet::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, rules)| *rules)
+    }
+
+    /// Whether the piece on the source may shove the target piece standing on
+    /// the target location, on a board of the given size, leaving aside
+    /// whether the two are adjacent, and if not, why
+    pub fn can_shove(
+        &self,
+        piece: StandardPiece,
+        source: GridLocation,
+        target_piece: StandardPiece,
+        target_location: GridLocation,
+        size: usize,
+    ) -> Result<(), ShoveError> {
+        if matches!(target_piece.kind, Kind::Monarch) && !self.shove_monarchs {
+            return Err(ShoveError::Monarch);
+        }
+        match piece.kind {
+            Kind::Monarch => Ok(()),
+            Kind::Pawn if self.pawn_shoves.allows(source, target_location, size) => Ok(()),
+            Kind::Pawn => Err(ShoveError::TooCentral),
+        }
+    }
+
+    /// Whether the given piece may make a null move
+    pub fn can_pass(&self, piece: StandardPiece) -> bool {
+        self.null_moves && matches!(piece.kind, Kind::Monarch)
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet::LEISERCHESS_2023
+    }
+}
+
+#[cfg(test)]
+pub mod rules_tests {
+    use super::*;
+
+    #[test]
+    pub fn presets_decide_shoves() {
+        let monarch = StandardPiece::from_str("NN").unwrap();
+        let pawn = StandardPiece::from_str("ne").unwrap();
+        let c3 = GridLocation::from_str("c3").unwrap();
+        let d4 = GridLocation::from_str("d4").unwrap();
+        let f3 = GridLocation::from_str("f3").unwrap();
+        assert_eq!(c3.qi(BOARD_SIZE), f3.qi(BOARD_SIZE));
+
+        let rules = RuleSet::default();
+        assert_eq!(
+            rules.can_shove(monarch, d4, monarch, c3, BOARD_SIZE),
+            Err(ShoveError::Monarch)
+        );
+        assert!(rules.can_shove(monarch, c3, pawn, d4, BOARD_SIZE).is_ok());
+        assert!(rules.can_shove(pawn, d4, pawn, c3, BOARD_SIZE).is_ok());
+        assert_eq!(
+            rules.can_shove(pawn, c3, pawn, d4, BOARD_SIZE),
+            Err(ShoveError::TooCentral)
+        );
+        assert!(rules.can_shove(pawn, c3, pawn, f3, BOARD_SIZE).is_err());
+
+        let rules = RuleSet::preset("Relaxed").unwrap();
+        assert!(rules
+            .can_shove(monarch, d4, monarch, c3, BOARD_SIZE)
+            .is_ok());
+        assert!(rules.can_shove(pawn, c3, pawn, f3, BOARD_SIZE).is_ok());
+        assert!(rules.can_shove(pawn, c3, pawn, d4, BOARD_SIZE).is_err());
+        assert_eq!(RuleSet::preset("2023"), Some(RuleSet::LEISERCHESS_2023));
+        assert_eq!(RuleSet::preset("1999"), None);
+    }
+}
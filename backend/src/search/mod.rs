@@ -0,0 +1,56 @@
+mod searcher;
+mod time;
+mod transposition;
+
+pub use searcher::*;
+pub use time::*;
+pub use transposition::*;
+
+use crate::action::standard::StandardAction;
+use std::time::Duration;
+
+/// Score of a position in which the side to move has already won,
+/// reduced by one for every action it takes to get there
+pub const WIN_SCORE: i32 = 1_000_000;
+
+/// Bound on every score the search can return
+pub const INFINITY: i32 = WIN_SCORE + 1;
+
+/// Deepest iteration the search will ever attempt
+pub const MAX_DEPTH: usize = 64;
+
+/// True if the score means one of the players can force a win
+pub fn is_win_score(score: i32) -> bool {
+    score.abs() > WIN_SCORE - MAX_DEPTH as i32
+}
+
+/// When to stop searching. The search stops as soon as any limit is reached,
+/// and runs until MAX_DEPTH or an external stop if none are given
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    /// Deepest iteration to complete
+    pub depth: Option<usize>,
+    /// Number of positions to visit
+    pub nodes: Option<u64>,
+    /// Time to spend searching
+    pub time: Option<Duration>,
+    /// Clock of the side to move, from which the time to spend is planned
+    pub clock: Option<Clock>,
+}
+
+/// The outcome of the deepest completed iteration of a search
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    /// The action to take, or None if the game is already over
+    pub best_action: Option<StandardAction>,
+    /// Score of the position from the perspective of the side to move
+    pub score: i32,
+    /// Depth of the deepest completed iteration
+    pub depth: usize,
+    /// Number of positions visited across every iteration
+    pub nodes: u64,
+    /// Time spent searching across every iteration
+    pub elapsed: Duration,
+    /// Best line of play found, starting with the best action
+    pub pv: Vec<StandardAction>,
+}
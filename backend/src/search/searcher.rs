@@ -0,0 +1,390 @@
+use super::*;
+use crate::eval::{self, EvalParams};
+use crate::game::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of positions visited between checks of the clock and the stop flag
+const CHECK_INTERVAL: u64 = 256;
+
+/// Negamax alpha-beta search with iterative deepening,
+/// run on top of a game so that the repetition rule is respected
+pub struct Searcher {
+    limits: SearchLimits,
+    params: EvalParams,
+    config: ResultConfig,
+    stop: Arc<AtomicBool>,
+    start: Instant,
+    /// Time after which the search is abandoned
+    deadline: Option<Duration>,
+    time_manager: Option<TimeManager>,
+    nodes: u64,
+    stopped: bool,
+    table: Arc<TranspositionTable>,
+}
+
+impl Searcher {
+    pub fn new(limits: SearchLimits) -> Self {
+        Searcher::with_params(limits, EvalParams::default())
+    }
+
+    /// A searcher that evaluates positions with the given weights
+    pub fn with_params(limits: SearchLimits, params: EvalParams) -> Self {
+        Searcher {
+            limits,
+            params,
+            config: ResultConfig::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+            start: Instant::now(),
+            deadline: None,
+            time_manager: None,
+            nodes: 0,
+            stopped: false,
+            table: Arc::new(TranspositionTable::default()),
+        }
+    }
+
+    /// The transposition table the searcher reads and fills
+    pub fn table(&self) -> &Arc<TranspositionTable> {
+        &self.table
+    }
+
+    /// Search with the given table, which may be shared
+    /// with other searchers running at the same time
+    pub fn set_table(&mut self, table: Arc<TranspositionTable>) {
+        self.table = table;
+    }
+
+    /// Flag that stops the search as soon as possible once set,
+    /// for use from another thread
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Search the current position of the game, leaving it as it was
+    pub fn search<const N: usize>(&mut self, game: &mut GridGame<N>) -> SearchResult {
+        self.search_with(game, |_| {})
+    }
+
+    /// Search the current position of the game, calling report
+    /// with the result of every completed iteration
+    pub fn search_with<const N: usize, F: FnMut(&SearchResult)>(
+        &mut self,
+        game: &mut GridGame<N>,
+        mut report: F,
+    ) -> SearchResult {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.table.new_search();
+        self.time_manager = self.limits.clock.map(|clock| TimeManager::new(&clock));
+        self.deadline = match (self.limits.time, self.time_manager) {
+            (Some(time), Some(manager)) => Some(time.min(manager.hard_deadline())),
+            (time, manager) => time.or(manager.map(|m| m.hard_deadline())),
+        };
+
+        let mut result = SearchResult::default();
+        if game.result(&self.config).is_over() {
+            return result;
+        }
+
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let forced = game.legal_actions().len() == 1;
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let score = self.negamax(game, depth, 0, -INFINITY, INFINITY, &mut pv);
+            if self.stopped {
+                break;
+            }
+
+            if let Some(manager) = &mut self.time_manager {
+                if depth > 1 && pv.first() != result.best_action.as_ref() {
+                    manager.best_action_changed();
+                }
+            }
+            result = SearchResult {
+                best_action: pv.first().cloned(),
+                score,
+                depth,
+                nodes: self.nodes,
+                elapsed: self.start.elapsed(),
+                pv: pv.clone(),
+            };
+            report(&result);
+
+            // No deeper iteration can improve on a forced win
+            if is_win_score(score) {
+                break;
+            }
+            // Thinking about a forced action only wastes the clock
+            if forced && self.limits.clock.is_some() {
+                break;
+            }
+            if let Some(manager) = &self.time_manager {
+                if !manager.can_continue(self.start.elapsed()) {
+                    break;
+                }
+            }
+        }
+
+        // Even an interrupted first iteration must suggest an action
+        if result.best_action.is_none() {
+            result.best_action = game.legal_actions().into_iter().next();
+        }
+        result.nodes = self.nodes;
+        result.elapsed = self.start.elapsed();
+        result
+    }
+
+    /// Score of the position from the perspective of the side to move,
+    /// filling pv with the best line found from here
+    fn negamax<const N: usize>(
+        &mut self,
+        game: &mut GridGame<N>,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<StandardAction>,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            self.stopped = true;
+            return 0;
+        }
+
+        match game.result(&self.config) {
+            GameResult::Win { winner, .. } if winner == game.current_player() => {
+                return WIN_SCORE - ply as i32
+            }
+            GameResult::Win { .. } => return -(WIN_SCORE - ply as i32),
+            GameResult::Draw { .. } => return 0,
+            GameResult::InProgress => {}
+        }
+        if depth == 0 {
+            return eval::evaluate(game.board(), game.current_player(), &self.params);
+        }
+
+        let hash = game.zobrist();
+        let is_pv = beta - alpha > 1;
+        let entry = self.table.probe(hash);
+        if let Some(entry) = &entry {
+            // Cutting off principal variation nodes would truncate the line
+            if !is_pv && entry.depth >= depth {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut actions = game.legal_actions();
+        if actions.is_empty() {
+            return 0;
+        }
+        // Try the best action from an earlier search of this position first
+        if let Some(preferred) = entry.and_then(|entry| entry.best_action) {
+            if let Some(index) = actions.iter().position(|action| *action == preferred) {
+                actions[..=index].rotate_right(1);
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_action = None;
+        let mut searched = 0;
+        for action in actions {
+            if game.apply(action.clone()).is_err() {
+                continue;
+            }
+            let mut line = Vec::new();
+            // Prove later actions are worse with a null window,
+            // searching them fully only if that fails
+            let mut score = if searched == 0 {
+                -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, &mut line)
+            } else {
+                -self.negamax(game, depth - 1, ply + 1, -alpha - 1, -alpha, &mut line)
+            };
+            if searched > 0 && !self.stopped && score > alpha && score < beta {
+                line.clear();
+                score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            }
+            game.undo().expect("Applied action cannot be undone");
+            if self.stopped {
+                return 0;
+            }
+            searched += 1;
+
+            if score > best {
+                best = score;
+                best_action = Some(action.clone());
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(action);
+                pv.extend(line);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        if searched == 0 {
+            return 0;
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(
+            hash,
+            &TableEntry {
+                depth,
+                bound,
+                score: to_table(best, ply),
+                best_action: best_action.filter(|_| bound != Bound::Upper),
+            },
+        );
+        best
+    }
+
+    fn should_stop(&self) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes > nodes) {
+            return true;
+        }
+        if !self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            return false;
+        }
+        self.stop.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| self.start.elapsed() >= deadline)
+    }
+}
+
+/// Make a winning score relative to the position being stored rather than
+/// the root, since the same position can be reached at different plies
+fn to_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if is_win_score(score) => score + score.signum() * ply as i32,
+        score => score,
+    }
+}
+
+/// Make a winning score read from the table relative to the root again
+fn from_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if is_win_score(score) => score - score.signum() * ply as i32,
+        score => score,
+    }
+}
+
+#[cfg(test)]
+pub mod searcher_tests {
+    use super::*;
+    use crate::board::Parseable;
+    use crate::constants::STARTING_POSITION;
+
+    fn limited_to(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    pub fn finds_winning_zap() {
+        // Turning the monarch on a8 around zaps the only black monarch
+        let mut game = StandardGame::from_fen("nn7/8/8/8/8/8/8/SS7 W").unwrap();
+        let result = Searcher::new(limited_to(3)).search(&mut game);
+        assert_eq!(result.best_action.unwrap().to_string(), "a8U");
+        assert_eq!(result.score, WIN_SCORE - 1);
+        assert_eq!(result.depth, 1);
+        assert_eq!(game.history_length(), 0);
+    }
+
+    #[test]
+    pub fn solves_other_board_sizes() {
+        let mut game: GridGame<4> = Game::from_fen("nn3/4/4/SS3 W").unwrap();
+        let result = Searcher::new(limited_to(3)).search(&mut game);
+        assert_eq!(result.best_action.unwrap().to_string(), "a4U");
+        assert_eq!(result.score, WIN_SCORE - 1);
+
+        let mut game: GridGame<16> =
+            Game::from_fen("nn15/16/16/16/16/16/16/16/16/16/16/16/16/16/16/SS15 W").unwrap();
+        let result = Searcher::new(limited_to(2)).search(&mut game);
+        assert_eq!(result.best_action.unwrap().to_string(), "a16U");
+        assert_eq!(result.score, WIN_SCORE - 1);
+    }
+
+    #[test]
+    pub fn principal_variation_is_playable() {
+        let mut game = StandardGame::from_fen(STARTING_POSITION).unwrap();
+        let mut depths = vec![];
+        let result = Searcher::new(limited_to(3))
+            .search_with(&mut game, |iteration| depths.push(iteration.depth));
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(result.best_action.as_ref(), result.pv.first());
+
+        let fen = game.to_fen();
+        for action in result.pv {
+            game.apply(action).unwrap();
+        }
+        assert_ne!(game.to_fen(), fen);
+    }
+
+    #[test]
+    pub fn respects_node_limit() {
+        let mut game = StandardGame::from_fen(STARTING_POSITION).unwrap();
+        let limits = SearchLimits {
+            nodes: Some(1000),
+            ..SearchLimits::default()
+        };
+        let result = Searcher::new(limits).search(&mut game);
+        assert!(result.nodes <= 1001);
+        assert!(result.best_action.is_some());
+        assert_eq!(game.to_fen(), STARTING_POSITION);
+    }
+
+    #[test]
+    pub fn table_is_reused_between_searches() {
+        let mut game = StandardGame::from_fen(STARTING_POSITION).unwrap();
+        let mut searcher = Searcher::new(limited_to(3));
+        let first = searcher.search(&mut game);
+        let second = searcher.search(&mut game);
+        assert!(second.nodes < first.nodes);
+        assert_eq!(second.score, first.score);
+
+        searcher.table().clear();
+        assert_eq!(searcher.search(&mut game).nodes, first.nodes);
+    }
+
+    #[test]
+    pub fn clock_limits_search_time() {
+        let mut game = StandardGame::from_fen(STARTING_POSITION).unwrap();
+        let limits = SearchLimits {
+            clock: Some(Clock {
+                remaining: Duration::from_millis(1000),
+                increment: Duration::ZERO,
+                moves_to_go: None,
+            }),
+            ..SearchLimits::default()
+        };
+        let result = Searcher::new(limits).search(&mut game);
+        assert!(result.best_action.is_some());
+        assert!(result.elapsed < Duration::from_millis(600));
+        assert!(result.depth >= 1);
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+/// Number of actions assumed to remain when the clock does not say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// Time kept in reserve for communication with the GUI
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// How much longer than the soft deadline the search may ever run
+const HARD_LIMIT_FACTOR: u32 = 4;
+
+/// The state of the mover's clock in a timed game
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clock {
+    /// Time left on the clock
+    pub remaining: Duration,
+    /// Time added to the clock after every action, as in Fischer time controls
+    pub increment: Duration,
+    /// Number of actions to take before the clock is next topped up, if any
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long a search may take given the state of the clock.
+///
+/// No new iteration is started after the soft deadline, which grows
+/// while the search keeps changing its mind. The search is abandoned
+/// altogether at the hard deadline
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    soft: Duration,
+    hard: Duration,
+}
+
+impl TimeManager {
+    pub fn new(clock: &Clock) -> Self {
+        let available = clock.remaining.saturating_sub(MOVE_OVERHEAD);
+        let moves_to_go = clock
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .clamp(1, DEFAULT_MOVES_TO_GO);
+
+        // The increment arrives after every action, so most of it can be spent now
+        let share = available / moves_to_go + clock.increment * 3 / 4;
+        let hard = (share * HARD_LIMIT_FACTOR)
+            .min(available / 2)
+            .max(share.min(available));
+        TimeManager {
+            soft: share.min(hard),
+            hard,
+        }
+    }
+
+    /// Time after which no new iteration should be started
+    pub fn soft_deadline(&self) -> Duration {
+        self.soft
+    }
+
+    /// Time after which the search must stop
+    pub fn hard_deadline(&self) -> Duration {
+        self.hard
+    }
+
+    /// Allow more time for the next iteration, as the best action changed
+    /// in the last one and is therefore not yet to be trusted
+    pub fn best_action_changed(&mut self) {
+        self.soft = (self.soft * 3 / 2).min(self.hard);
+    }
+
+    /// True if there is time to start another iteration after the given time
+    pub fn can_continue(&self, elapsed: Duration) -> bool {
+        elapsed < self.soft
+    }
+}
+
+#[cfg(test)]
+pub mod time_tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    pub fn deadlines_follow_the_clock() {
+        let manager = TimeManager::new(&Clock {
+            remaining: seconds(60),
+            ..Clock::default()
+        });
+        assert!(manager.soft_deadline() > seconds(1));
+        assert!(manager.soft_deadline() < seconds(3));
+        assert!(manager.hard_deadline() > manager.soft_deadline());
+        assert!(manager.hard_deadline() <= seconds(30));
+
+        let with_increment = TimeManager::new(&Clock {
+            remaining: seconds(60),
+            increment: seconds(2),
+            moves_to_go: None,
+        });
+        assert!(with_increment.soft_deadline() > manager.soft_deadline() + seconds(1));
+
+        let last_move = TimeManager::new(&Clock {
+            remaining: seconds(10),
+            increment: Duration::ZERO,
+            moves_to_go: Some(1),
+        });
+        assert!(last_move.hard_deadline() < seconds(10));
+        assert!(last_move.soft_deadline() >= seconds(4));
+    }
+
+    #[test]
+    pub fn never_plans_past_the_flag() {
+        for millis in [0, 10, 50, 200] {
+            let remaining = Duration::from_millis(millis);
+            let manager = TimeManager::new(&Clock {
+                remaining,
+                increment: seconds(1),
+                moves_to_go: None,
+            });
+            assert!(manager.hard_deadline() <= remaining);
+            assert!(manager.soft_deadline() <= manager.hard_deadline());
+        }
+    }
+
+    #[test]
+    pub fn changing_best_action_extends_search() {
+        let mut manager = TimeManager::new(&Clock {
+            remaining: seconds(60),
+            ..Clock::default()
+        });
+        let soft = manager.soft_deadline();
+        assert!(!manager.can_continue(soft));
+
+        manager.best_action_changed();
+        assert!(manager.can_continue(soft));
+        for _ in 0..10 {
+            manager.best_action_changed();
+        }
+        assert_eq!(manager.soft_deadline(), manager.hard_deadline());
+    }
+}
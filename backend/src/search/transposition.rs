@@ -0,0 +1,294 @@
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use crate::constants::*;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Size of the table a searcher uses unless told otherwise
+pub const DEFAULT_TABLE_SIZE_MB: usize = 16;
+
+/// Number of distinct ages before they wrap around
+const AGES: u8 = 64;
+
+/// How a stored score relates to the true score of the position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The true score is at least the stored score
+    Lower,
+    /// The true score is at most the stored score
+    Upper,
+}
+
+/// What the table remembers about a position
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableEntry {
+    /// Depth the position was searched to
+    pub depth: usize,
+    pub bound: Bound,
+    pub score: i32,
+    /// Best action found, if any action raised alpha
+    pub best_action: Option<StandardAction>,
+}
+
+/// One slot of the table. The key is stored xored with the data, so that
+/// a slot torn by two threads writing at once fails verification
+/// instead of returning another position's data
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A fixed size, lock free hash table of search results keyed by the
+/// Zobrist hash of a position, which can be shared between threads.
+///
+/// Data is packed into a u64 as follows, from the lowest bit:
+/// 19 bits of best action, 6 bits of age, 2 bits of bound,
+/// 8 bits of depth and 29 bits of score, which is plenty
+/// as scores never go beyond INFINITY
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// A table taking up at most the given number of megabytes,
+    /// holding a power of two number of entries
+    pub fn new(megabytes: usize) -> Self {
+        TranspositionTable {
+            slots: Self::allocate(megabytes),
+            age: AtomicU8::new(0),
+        }
+    }
+
+    fn allocate(megabytes: usize) -> Vec<Slot> {
+        let capacity = (megabytes * 1024 * 1024 / std::mem::size_of::<Slot>()).max(1);
+        // Round down to a power of two so the hash can be masked into an index
+        let entries = 1 << capacity.ilog2();
+        (0..entries).map(|_| Slot::default()).collect()
+    }
+
+    /// Number of entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Reallocate the table with a new size, forgetting every entry
+    pub fn resize(&mut self, megabytes: usize) {
+        self.slots = Self::allocate(megabytes);
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Forget every entry
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Mark the start of a new search, so that entries from
+    /// earlier searches are the first to be replaced
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) % AGES, Ordering::Relaxed);
+    }
+
+    fn slot(&self, hash: u64) -> &Slot {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    /// The entry stored for the position with the given hash, if any
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = self.slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        if data == 0 || key ^ data != hash {
+            return None;
+        }
+        Some(unpack(data).0)
+    }
+
+    /// Remember the result of searching the position with the given hash.
+    ///
+    /// An entry for another position is only replaced if it comes from an
+    /// earlier search or was searched no deeper than this one
+    pub fn store(&self, hash: u64, entry: &TableEntry) {
+        let slot = self.slot(hash);
+        let age = self.age.load(Ordering::Relaxed);
+        let old_data = slot.data.load(Ordering::Relaxed);
+        let old_key = slot.key.load(Ordering::Relaxed) ^ old_data;
+
+        let mut entry = entry.clone();
+        if old_data != 0 {
+            let (old, old_age) = unpack(old_data);
+            if old_key == hash {
+                // Keep the known best action rather than forgetting it
+                if entry.best_action.is_none() {
+                    entry.best_action = old.best_action;
+                }
+            } else if old_age == age && old.depth > entry.depth {
+                return;
+            }
+        }
+
+        let data = pack(&entry, age);
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(DEFAULT_TABLE_SIZE_MB)
+    }
+}
+
+fn pack(entry: &TableEntry, age: u8) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    let action = entry.best_action.as_ref().map_or(0, encode_action);
+    let depth = entry.depth.min(u8::MAX as usize) as u64;
+    action | (age as u64) << 19 | bound << 25 | depth << 27 | (entry.score as i64 as u64) << 35
+}
+
+fn unpack(data: u64) -> (TableEntry, u8) {
+    let bound = match (data >> 25) & 0b11 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let entry = TableEntry {
+        depth: ((data >> 27) & 0xff) as usize,
+        bound,
+        // Shifting the signed value brings back the sign of the score
+        score: ((data as i64) >> 35) as i32,
+        best_action: decode_action(data & 0x7ffff),
+    };
+    (entry, ((data >> 19) & 0x3f) as u8)
+}
+
+/// Pack an action into 19 bits: 8 bits of source square, 8 bits of
+/// destination square, 2 bits of rotation and a bit marking it present.
+/// Victims are not stored, as generated actions never have any.
+/// Squares are numbered as on the largest board, which also
+/// covers every square of the smaller ones
+fn encode_action(action: &StandardAction) -> u64 {
+    let square = |location: GridLocation| (location.y * MAX_BOARD_SIZE + location.x) as u64;
+    let rotation = match action.relative_rotation() {
+        None => 0,
+        Some(Rotation::Right) => 1,
+        Some(Rotation::Left) => 2,
+        Some(Rotation::UTurn) => 3,
+    };
+    1 << 18 | rotation << 16 | square(action.destination()) << 8 | square(action.source())
+}
+
+fn decode_action(bits: u64) -> Option<StandardAction> {
+    if bits & 1 << 18 == 0 {
+        return None;
+    }
+    let square = |index: u64| GridLocation {
+        x: index as usize % MAX_BOARD_SIZE,
+        y: index as usize / MAX_BOARD_SIZE,
+    };
+    let rotation = match (bits >> 16) & 0b11 {
+        0 => None,
+        1 => Some(Rotation::Right),
+        2 => Some(Rotation::Left),
+        _ => Some(Rotation::UTurn),
+    };
+    Some(StandardAction::new(
+        vec![],
+        square(bits & 0xff),
+        square((bits >> 8) & 0xff),
+        rotation,
+    ))
+}
+
+#[cfg(test)]
+pub mod transposition_tests {
+    use super::*;
+    use crate::search::INFINITY;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn entry(depth: usize, score: i32, action: &str) -> TableEntry {
+        TableEntry {
+            depth,
+            bound: Bound::Lower,
+            score,
+            best_action: Some(StandardAction::from_str(action).unwrap()),
+        }
+    }
+
+    #[test]
+    pub fn entries_round_trip() {
+        let table = TranspositionTable::new(1);
+        assert!(table.capacity().is_power_of_two());
+        assert_eq!(table.capacity(), 1024 * 1024 / 16);
+
+        for (hash, entry) in [
+            (0x1234, entry(7, -1_000_000, "h8U")),
+            (0x5678, entry(0, 42, "a1b2")),
+            (0x9abc, entry(255, 0, "d4d4")),
+            (0xdef0, entry(12, INFINITY, "p16o15")),
+            (0x0fed, entry(1, -INFINITY, "a16R")),
+        ] {
+            table.store(hash, &entry);
+            assert_eq!(table.probe(hash), Some(entry));
+        }
+        assert_eq!(table.probe(0x4321), None);
+
+        table.clear();
+        assert_eq!(table.probe(0x1234), None);
+    }
+
+    #[test]
+    pub fn replacement_prefers_deep_and_recent_entries() {
+        let table = TranspositionTable::new(1);
+        let capacity = table.capacity() as u64;
+        let (first, second) = (3, 3 + capacity);
+
+        table.store(first, &entry(5, 10, "a1b2"));
+        table.store(second, &entry(2, 20, "a1b2"));
+        assert_eq!(table.probe(first).unwrap().score, 10);
+        assert_eq!(table.probe(second), None);
+
+        // Entries from an earlier search are always replaced
+        table.new_search();
+        table.store(second, &entry(2, 20, "a1b2"));
+        assert_eq!(table.probe(second).unwrap().score, 20);
+
+        // Storing a position again keeps its best action
+        let mut update = entry(3, 30, "a1b2");
+        update.best_action = None;
+        table.store(second, &update);
+        assert_eq!(
+            table.probe(second).unwrap().best_action,
+            entry(0, 0, "a1b2").best_action
+        );
+    }
+
+    #[test]
+    pub fn table_is_shared_between_threads() {
+        let table = Arc::new(TranspositionTable::new(1));
+        let handles: Vec<_> = (0..4u64)
+            .map(|i| {
+                let table = table.clone();
+                thread::spawn(move || table.store(i + 1, &entry(i as usize, i as i32, "e4R")))
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for i in 0..4u64 {
+            assert_eq!(table.probe(i + 1).unwrap().score, i as i32);
+        }
+    }
+}
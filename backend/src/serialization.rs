@@ -0,0 +1,330 @@
+//! Serde support for boards, pieces and actions, enabled by the `serde` feature.
+//!
+//! The schema, shown as JSON, is kept stable so that stored fixtures and
+//! other tools keep working:
+//!
+//! - `Color` is `"white"` or `"black"`
+//! - `Kind` is `"monarch"` or `"pawn"`
+//! - `Direction` is one of `"N"`, `"NE"`, `"E"`, `"SE"`, `"S"`, `"SW"`, `"W"`, `"NW"`
+//! - `Rotation` is `"R"` (clockwise), `"L"` (counter clockwise) or `"U"`
+//! - `GridLocation` is a square such as `"e4"`
+//! - `StandardPiece` is `{"color": "white", "kind": "pawn", "direction": "NE"}`
+//! - `Grid` is `{"pieces": [{"square": "a8", "color": "white",
+//!   "kind": "monarch", "direction": "S"}, ...]}`, listing pieces in FEN order,
+//!   with the size of the board known by the reader rather than written
+//! - `Position` is `{"board": <Grid>, "player": <Color>}`
+//! - `StandardAction` is `{"source": "e3", "destination": "e4", "rotation": null,
+//!   "victims": []}`, where rotations have the same source and destination
+//! - `GameRecord` is `{"tags": [{"name": "Event", "value": "..."}, ...],
+//!   "start": "<FEN> W", "actions": [<StandardAction>, ...]}`, where `tags` may be left out
+//!
+//! Pieces must point the way their kind allows, so monarchs face
+//! orthogonally and pawns diagonally, or deserialization fails
+
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+/// Implement Serialize and Deserialize for a type written as one of a fixed set of strings
+macro_rules! string_serde {
+    ($type:ty, $expecting:literal, { $($value:expr => $name:literal),+ $(,)? }) => {
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let names: &[($type, &str)] = &[$(($value, $name)),+];
+                let (_, name) = names
+                    .iter()
+                    .find(|(value, _)| value == self)
+                    .expect("Every value must have a name");
+                serializer.serialize_str(name)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let name = String::deserialize(deserializer)?;
+                match name.as_str() {
+                    $($name => Ok($value),)+
+                    _ => Err(de::Error::invalid_value(de::Unexpected::Str(&name), &$expecting)),
+                }
+            }
+        }
+    };
+}
+
+string_serde!(Color, "white or black", {
+    Color::White => "white",
+    Color::Black => "black",
+});
+
+string_serde!(Kind, "monarch or pawn", {
+    Kind::Monarch => "monarch",
+    Kind::Pawn => "pawn",
+});
+
+string_serde!(Direction, "a compass direction such as N or SE", {
+    Direction::Orthogonal(Orthogonal::North) => "N",
+    Direction::Diagonal(Diagonal::NorthEast) => "NE",
+    Direction::Orthogonal(Orthogonal::East) => "E",
+    Direction::Diagonal(Diagonal::SouthEast) => "SE",
+    Direction::Orthogonal(Orthogonal::South) => "S",
+    Direction::Diagonal(Diagonal::SouthWest) => "SW",
+    Direction::Orthogonal(Orthogonal::West) => "W",
+    Direction::Diagonal(Diagonal::NorthWest) => "NW",
+});
+
+string_serde!(Rotation, "R, L or U", {
+    Rotation::Right => "R",
+    Rotation::Left => "L",
+    Rotation::UTurn => "U",
+});
+
+impl Serialize for GridLocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for GridLocation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let square = String::deserialize(deserializer)?;
+        GridLocation::from_str(&square).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PieceFields {
+    color: Color,
+    kind: Kind,
+    direction: Direction,
+}
+
+impl Serialize for StandardPiece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PieceFields {
+            color: self.color,
+            kind: self.kind,
+            direction: self.direction,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StandardPiece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let PieceFields {
+            color,
+            kind,
+            direction,
+        } = PieceFields::deserialize(deserializer)?;
+        match (kind, direction) {
+            (Kind::Monarch, Direction::Orthogonal(_)) | (Kind::Pawn, Direction::Diagonal(_)) => {
+                Ok(StandardPiece {
+                    color,
+                    kind,
+                    direction,
+                })
+            }
+            _ => Err(de::Error::custom(format!(
+                "a {:?} cannot face {:?}",
+                kind, direction
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlacedPiece {
+    square: GridLocation,
+    #[serde(flatten)]
+    piece: StandardPiece,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GridFields {
+    pieces: Vec<PlacedPiece>,
+}
+
+impl<const N: usize> Serialize for Grid<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridFields {
+            pieces: self
+                .pieces()
+                .map(|(square, piece)| PlacedPiece { square, piece })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Grid<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = GridFields::deserialize(deserializer)?;
+        let mut grid = Grid::empty();
+        for PlacedPiece { square, piece } in fields.pieces {
+            if grid.validate_location(&square).is_err() {
+                return Err(de::Error::custom(format!(
+                    "{} is off a board of size {}",
+                    square.to_string(),
+                    N
+                )));
+            }
+            if grid.get_unchecked(&square).is_some() {
+                return Err(de::Error::custom(format!(
+                    "more than one piece on {}",
+                    square.to_string()
+                )));
+            }
+            grid.set_unchecked(&square, piece);
+        }
+        Ok(grid)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PositionFields<B> {
+    board: B,
+    player: Color,
+}
+
+impl<B: Serialize> Serialize for Position<B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PositionFields {
+            board: &self.board,
+            player: self.player,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, B: Deserialize<'de>> Deserialize<'de> for Position<B> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let PositionFields { board, player } = PositionFields::deserialize(deserializer)?;
+        Ok(Position::new(board, player))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ActionFields {
+    source: GridLocation,
+    destination: GridLocation,
+    #[serde(default)]
+    rotation: Option<Rotation>,
+    #[serde(default)]
+    victims: Vec<GridLocation>,
+}
+
+impl Serialize for StandardAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ActionFields {
+            source: self.source(),
+            destination: self.destination(),
+            rotation: self.relative_rotation(),
+            victims: self.victims().to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StandardAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = ActionFields::deserialize(deserializer)?;
+        if fields.rotation.is_some() && fields.source != fields.destination {
+            return Err(de::Error::custom(
+                "a rotation must have the same source and destination",
+            ));
+        }
+        Ok(StandardAction::new(
+            fields.victims,
+            fields.source,
+            fields.destination,
+            fields.rotation,
+        ))
+    }
+}
+
+#[cfg(test)]
+pub mod serialization_tests {
+    use super::*;
+    use crate::constants::STARTING_POSITION;
+    use crate::game::{Game, GameRecord, StandardGame};
+    use serde_json::json;
+
+    #[test]
+    pub fn pieces_and_boards_follow_schema() {
+        let grid = LeiserChessGrid::from_str("SS7/8/8/8/8/8/8/7ne").unwrap();
+        let value = serde_json::to_value(&grid).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "pieces": [
+                    {"square": "a1", "color": "white", "kind": "monarch", "direction": "S"},
+                    {"square": "h8", "color": "black", "kind": "pawn", "direction": "NE"},
+                ]
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<LeiserChessGrid>(value).unwrap(),
+            grid
+        );
+
+        let position = Position::<LeiserChessGrid>::from_str(STARTING_POSITION).unwrap();
+        let json = serde_json::to_string(&position).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Position<_>>(&json).unwrap(),
+            position
+        );
+    }
+
+    #[test]
+    pub fn actions_and_records_round_trip() {
+        let action = StandardAction::from_str("d4R").unwrap();
+        let value = serde_json::to_value(&action).unwrap();
+        assert_eq!(
+            value,
+            json!({"source": "d4", "destination": "d4", "rotation": "R", "victims": []})
+        );
+        assert_eq!(
+            serde_json::from_value::<StandardAction>(value).unwrap(),
+            action
+        );
+        assert_eq!(
+            serde_json::from_value::<StandardAction>(json!({"source": "e3", "destination": "e4"}))
+                .unwrap(),
+            StandardAction::from_str("e3e4").unwrap()
+        );
+
+        let mut game: StandardGame = Game::from_fen(STARTING_POSITION).unwrap();
+        game.apply(StandardAction::from_str("a7b6").unwrap())
+            .unwrap();
+        let record = GameRecord::from_game(&game);
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<GameRecord>(&json).unwrap(), record);
+    }
+
+    #[test]
+    pub fn rejects_invalid_data() {
+        for invalid in [
+            json!({"color": "white", "kind": "monarch", "direction": "NE"}),
+            json!({"color": "grey", "kind": "pawn", "direction": "NE"}),
+            json!({"color": "white", "kind": "pawn"}),
+        ] {
+            assert!(serde_json::from_value::<StandardPiece>(invalid).is_err());
+        }
+        assert!(serde_json::from_value::<GridLocation>(json!("q1")).is_err());
+        assert!(serde_json::from_value::<StandardAction>(
+            json!({"source": "d4", "destination": "d5", "rotation": "L"})
+        )
+        .is_err());
+        let twice = json!({"pieces": [
+            {"square": "a1", "color": "white", "kind": "monarch", "direction": "S"},
+            {"square": "a1", "color": "black", "kind": "pawn", "direction": "NE"},
+        ]});
+        assert!(serde_json::from_value::<LeiserChessGrid>(twice).is_err());
+        let outside = json!({"pieces": [
+            {"square": "e5", "color": "white", "kind": "monarch", "direction": "S"},
+        ]});
+        assert!(serde_json::from_value::<Grid<4>>(outside).is_err());
+    }
+}
@@ -0,0 +1,447 @@
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use crate::constants::*;
+use crate::game::{self, GameResult, ResultConfig, StandardGame};
+use crate::search::{SearchLimits, SearchResult, Searcher};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Time the engine thinks for unless the request says otherwise
+const DEFAULT_ENGINE_TIME: Duration = Duration::from_millis(1000);
+
+/// Longest the engine may think for
+const MAX_ENGINE_TIME: Duration = Duration::from_millis(10_000);
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Not found")]
+    NotFound,
+    #[error("Method not allowed")]
+    MethodNotAllowed,
+    #[error("The game changed while the engine was thinking")]
+    Conflict,
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Parse(#[from] ParseError),
+    #[error("{0}")]
+    Game(#[from] game::Error),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    fn status(&self) -> u16 {
+        match self {
+            Error::NotFound => 404,
+            Error::MethodNotAllowed => 405,
+            Error::Conflict => 409,
+            Error::Game(_) => 422,
+            _ => 400,
+        }
+    }
+}
+
+/// A JSON response to an API request
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+}
+
+/// A search for the engine's action, run on a copy of the game
+/// so that the server stays free for other requests meanwhile
+struct EngineSearch {
+    id: u64,
+    game: StandardGame,
+    limits: SearchLimits,
+}
+
+/// The engine's search along with the game it searched,
+/// as it was when the search started
+struct SearchedGame {
+    id: u64,
+    game: StandardGame,
+    result: SearchResult,
+}
+
+/// A request that is either answered or waiting on the engine
+enum Handled {
+    Done(Response),
+    Searching(Box<EngineSearch>),
+}
+
+struct ServedGame {
+    game: StandardGame,
+    /// Channels of the clients listening for updates to the game
+    subscribers: Vec<Sender<String>>,
+}
+
+/// The games served over the JSON API, independent of how requests arrive.
+///
+/// Routes:
+/// - POST /games with an optional "fen" creates a game
+/// - GET /games/{id} describes a game
+/// - GET /games/{id}/actions lists the legal actions
+/// - POST /games/{id}/actions with an "action" such as "e3e4" or "d4R" takes it
+/// - DELETE /games/{id} forgets a game and ends its event streams
+/// - POST /games/{id}/undo takes back the last action
+/// - POST /games/{id}/engine with an optional "depth" or "movetime"
+///   in milliseconds lets the engine take an action
+#[derive(Default)]
+pub struct GameServer {
+    games: HashMap<u64, ServedGame>,
+    next_id: u64,
+}
+
+impl GameServer {
+    pub fn new() -> Self {
+        GameServer::default()
+    }
+
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        let result = self
+            .route(method, path, body)
+            .and_then(|handled| match handled {
+                Handled::Done(response) => Ok(response),
+                Handled::Searching(search) => self.finish_engine_action(search.run()),
+            });
+        to_response(result)
+    }
+
+    /// Handle a request to a server shared between threads, holding the
+    /// lock while games are read or changed but not while the engine thinks
+    pub fn handle_shared(
+        server: &Mutex<GameServer>,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Response {
+        let handled = server
+            .lock()
+            .expect("Server lock poisoned")
+            .route(method, path, body);
+        let result = handled.and_then(|handled| match handled {
+            Handled::Done(response) => Ok(response),
+            Handled::Searching(search) => {
+                let searched = search.run();
+                server
+                    .lock()
+                    .expect("Server lock poisoned")
+                    .finish_engine_action(searched)
+            }
+        });
+        to_response(result)
+    }
+
+    fn route(&mut self, method: &str, path: &str, body: &str) -> Result<Handled, Error> {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let response = match (method, segments.as_slice()) {
+            ("POST", ["games"]) => self.create(&parse_body(body)?),
+            (_, ["games"]) => Err(Error::MethodNotAllowed),
+            (method, ["games", id, rest @ ..]) => {
+                let id: u64 = id.parse().map_err(|_| Error::NotFound)?;
+                if !self.games.contains_key(&id) {
+                    return Err(Error::NotFound);
+                }
+                match (method, rest) {
+                    ("GET", []) => Ok(Response::ok(self.describe(id))),
+                    ("DELETE", []) => Ok(self.delete(id)),
+                    ("GET", ["actions"]) => Ok(Response::ok(self.legal_actions(id))),
+                    ("POST", ["actions"]) => self.take_action(id, &parse_body(body)?),
+                    ("POST", ["undo"]) => self.undo(id),
+                    ("POST", ["engine"]) => {
+                        let search = self.start_engine_action(id, &parse_body(body)?);
+                        return Ok(Handled::Searching(Box::new(search)));
+                    }
+                    (_, [] | ["actions"] | ["undo"] | ["engine"]) => Err(Error::MethodNotAllowed),
+                    _ => Err(Error::NotFound),
+                }
+            }
+            _ => Err(Error::NotFound),
+        };
+        response.map(Handled::Done)
+    }
+
+    /// Listen for every later update to the game, each sent as
+    /// the same JSON that GET /games/{id} returns
+    pub fn subscribe(&mut self, id: u64) -> Option<Receiver<String>> {
+        let served = self.games.get_mut(&id)?;
+        let (sender, receiver) = mpsc::channel();
+        served.subscribers.push(sender);
+        Some(receiver)
+    }
+
+    fn create(&mut self, request: &Value) -> Result<Response, Error> {
+        let fen = match request.get("fen") {
+            Some(Value::String(fen)) => fen.as_str(),
+            Some(_) => return Err(Error::BadRequest("fen must be a string".to_string())),
+            None => STARTING_POSITION,
+        };
+        let game = StandardGame::from_fen(fen)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.games.insert(
+            id,
+            ServedGame {
+                game,
+                subscribers: Vec::new(),
+            },
+        );
+        Ok(Response {
+            status: 201,
+            body: self.describe(id),
+        })
+    }
+
+    fn describe(&self, id: u64) -> Value {
+        let game = &self.games[&id].game;
+        let history: Vec<String> = game.actions().map(|a| a.to_string()).collect();
+        let zapped: Vec<String> = game
+            .last_zapped()
+            .iter()
+            .map(|(location, _)| location.to_string())
+            .collect();
+        json!({
+            "id": id,
+            "fen": game.to_fen(),
+            "player": game.current_player().to_string(),
+            "history": history,
+            "zapped": zapped,
+            "result": describe_result(game.result(&ResultConfig::default())),
+        })
+    }
+
+    fn legal_actions(&self, id: u64) -> Value {
+        let game = &self.games[&id].game;
+        let actions: Vec<String> = game.legal_actions().iter().map(|a| a.to_string()).collect();
+        json!({ "actions": actions })
+    }
+
+    fn take_action(&mut self, id: u64, request: &Value) -> Result<Response, Error> {
+        let action = match request.get("action") {
+            Some(Value::String(action)) => StandardAction::from_str(action)?,
+            _ => return Err(Error::BadRequest("action must be a string".to_string())),
+        };
+        let game = self.game_mut(id)?;
+        if game.result(&ResultConfig::default()).is_over() {
+            return Err(Error::BadRequest("The game is over".to_string()));
+        }
+        game.apply(action)?;
+        Ok(self.updated(id))
+    }
+
+    fn undo(&mut self, id: u64) -> Result<Response, Error> {
+        self.game_mut(id)?.undo()?;
+        Ok(self.updated(id))
+    }
+
+    /// Forget the game, which ends the event streams of its subscribers
+    fn delete(&mut self, id: u64) -> Response {
+        self.games.remove(&id);
+        Response::ok(json!({ "id": id, "deleted": true }))
+    }
+
+    fn start_engine_action(&self, id: u64, request: &Value) -> EngineSearch {
+        let depth = request.get("depth").and_then(Value::as_u64);
+        let time = request
+            .get("movetime")
+            .and_then(Value::as_u64)
+            .map_or(DEFAULT_ENGINE_TIME, Duration::from_millis)
+            .min(MAX_ENGINE_TIME);
+        EngineSearch {
+            id,
+            game: self.games[&id].game.clone(),
+            limits: SearchLimits {
+                depth: depth.map(|depth| depth as usize),
+                time: Some(time),
+                ..SearchLimits::default()
+            },
+        }
+    }
+
+    /// Take the action the engine found, unless the game
+    /// was changed or deleted while it was thinking
+    fn finish_engine_action(&mut self, searched: SearchedGame) -> Result<Response, Error> {
+        let SearchedGame { id, game, result } = searched;
+        let served = self.game_mut(id)?;
+        if !served.actions().eq(game.actions()) {
+            return Err(Error::Conflict);
+        }
+        let action = result
+            .best_action
+            .ok_or_else(|| Error::BadRequest("The game is over".to_string()))?;
+        served.apply(action.clone())?;
+
+        let mut response = self.updated(id);
+        response.body["engine"] = json!({
+            "action": action.to_string(),
+            "score": result.score,
+            "depth": result.depth,
+        });
+        Ok(response)
+    }
+
+    fn game_mut(&mut self, id: u64) -> Result<&mut StandardGame, Error> {
+        self.games
+            .get_mut(&id)
+            .map(|served| &mut served.game)
+            .ok_or(Error::NotFound)
+    }
+
+    /// Describe the game to the client and everyone listening to it
+    fn updated(&mut self, id: u64) -> Response {
+        let state = self.describe(id);
+        let message = state.to_string();
+        let served = self.games.get_mut(&id).expect("Updated game must exist");
+        // Forget clients that stopped listening
+        served
+            .subscribers
+            .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        Response::ok(state)
+    }
+}
+
+impl EngineSearch {
+    fn run(mut self) -> SearchedGame {
+        let result = Searcher::new(self.limits).search(&mut self.game);
+        SearchedGame {
+            id: self.id,
+            game: self.game,
+            result,
+        }
+    }
+}
+
+fn to_response(result: Result<Response, Error>) -> Response {
+    match result {
+        Ok(response) => response,
+        Err(e) => Response {
+            status: e.status(),
+            body: json!({ "error": e.to_string() }),
+        },
+    }
+}
+
+fn parse_body(body: &str) -> Result<Value, Error> {
+    if body.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+fn describe_result(result: GameResult) -> Value {
+    match result {
+        GameResult::InProgress => json!({ "status": "in_progress" }),
+        GameResult::Win { winner, reason } => json!({
+            "status": "win",
+            "winner": winner.to_string(),
+            "reason": format!("{:?}", reason),
+        }),
+        GameResult::Draw { reason } => json!({
+            "status": "draw",
+            "reason": format!("{:?}", reason),
+        }),
+    }
+}
+
+#[cfg(test)]
+pub mod server_tests {
+    use super::*;
+
+    #[test]
+    pub fn plays_a_game() {
+        let mut server = GameServer::new();
+        let created = server.handle("POST", "/games", "");
+        assert_eq!(created.status, 201);
+        assert_eq!(created.body["fen"], STARTING_POSITION);
+        assert_eq!(created.body["result"]["status"], "in_progress");
+
+        let actions = server.handle("GET", "/games/0/actions", "");
+        assert_eq!(actions.body["actions"].as_array().unwrap().len(), 64);
+
+        let updates = server.subscribe(0).unwrap();
+        let moved = server.handle("POST", "/games/0/actions", r#"{"action": "a7b6"}"#);
+        assert_eq!(moved.status, 200);
+        assert_eq!(moved.body["player"], "B");
+        assert_eq!(moved.body["history"], json!(["a7b6"]));
+        assert_eq!(updates.try_recv().unwrap(), moved.body.to_string());
+
+        let engine = server.handle("POST", "/games/0/engine", r#"{"depth": 1}"#);
+        assert_eq!(engine.status, 200);
+        assert_eq!(engine.body["player"], "W");
+        assert!(engine.body["engine"]["action"].is_string());
+
+        let undone = server.handle("POST", "/games/0/undo", "");
+        assert_eq!(undone.body["player"], "B");
+    }
+
+    #[test]
+    pub fn reports_errors() {
+        let mut server = GameServer::new();
+        assert_eq!(server.handle("GET", "/games/0", "").status, 404);
+        assert_eq!(
+            server
+                .handle("POST", "/games", r#"{"fen": "nn6nn/8 W"}"#)
+                .status,
+            400
+        );
+        assert_eq!(server.handle("POST", "/games", "{").status, 400);
+
+        server.handle("POST", "/games", r#"{"fen": "nn7/8/8/8/8/8/8/SS7 W"}"#);
+        assert_eq!(server.handle("PUT", "/games/0", "").status, 405);
+        let wrong_player = server.handle("POST", "/games/0/actions", r#"{"action": "a1R"}"#);
+        assert_eq!(wrong_player.status, 422);
+        assert!(wrong_player.body["error"].is_string());
+
+        let won = server.handle("POST", "/games/0/actions", r#"{"action": "a8U"}"#);
+        assert_eq!(won.body["result"]["status"], "win");
+        assert_eq!(won.body["zapped"], json!(["a1"]));
+        let late = server.handle("POST", "/games/0/actions", r#"{"action": "a1R"}"#);
+        assert_eq!(late.status, 400);
+    }
+
+    #[test]
+    pub fn deletes_games() {
+        let mut server = GameServer::new();
+        server.handle("POST", "/games", "");
+        let updates = server.subscribe(0).unwrap();
+        assert_eq!(server.handle("DELETE", "/games/0", "").status, 200);
+        assert_eq!(server.handle("GET", "/games/0", "").status, 404);
+        assert_eq!(server.handle("DELETE", "/games/0", "").status, 404);
+        // Streams end once the game is gone
+        assert!(updates.recv().is_err());
+    }
+
+    #[test]
+    pub fn engine_actions_yield_to_changes() {
+        let server = Mutex::new(GameServer::new());
+        GameServer::handle_shared(&server, "POST", "/games", "");
+        let search = server
+            .lock()
+            .unwrap()
+            .start_engine_action(0, &json!({"depth": 1}));
+        let searched = search.run();
+
+        // Another client moves while the engine thinks
+        GameServer::handle_shared(&server, "POST", "/games/0/actions", r#"{"action": "a7b6"}"#);
+        let stale = server.lock().unwrap().finish_engine_action(searched);
+        assert!(matches!(stale, Err(Error::Conflict)));
+        let engine =
+            GameServer::handle_shared(&server, "POST", "/games/0/engine", r#"{"depth": 1}"#);
+        assert_eq!(engine.status, 200);
+        assert_eq!(engine.body["history"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::action::standard::StandardAction;
+use crate::board::*;
+use crate::game::{GameResult, ResultConfig, StandardGame};
+use crate::laser;
+use wasm_bindgen::prelude::*;
+
+fn to_js_error(e: impl std::fmt::Display) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Check a FEN such as those written by GameState.toFEN, returning it
+/// as this crate would write it, or an error describing what is wrong
+#[wasm_bindgen(js_name = normalizeFen)]
+pub fn normalize_fen(fen: &str) -> Result<String, JsError> {
+    let position = Position::<LeiserChessGrid>::from_str(fen).map_err(to_js_error)?;
+    Ok(format!(
+        "{} {}",
+        position.board.to_string(),
+        position.player.to_string()
+    ))
+}
+
+/// A game driven from JavaScript, with the same rules as the rest of the
+/// crate. Squares and actions are written as in the frontend, such as
+/// "e4" for a square and "e3e4" or "d4R" for an action
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: StandardGame,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(fen: &str) -> Result<WasmGame, JsError> {
+        Ok(WasmGame {
+            game: StandardGame::from_fen(fen).map_err(to_js_error)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = toFEN)]
+    pub fn to_fen(&self) -> String {
+        self.game.to_fen()
+    }
+
+    /// "W" or "B"
+    #[wasm_bindgen(js_name = currentPlayer)]
+    pub fn current_player(&self) -> String {
+        self.game.current_player().to_string()
+    }
+
+    /// Every action the current player may take
+    #[wasm_bindgen(js_name = legalActions)]
+    pub fn legal_actions(&self) -> Vec<String> {
+        self.game
+            .legal_actions()
+            .iter()
+            .map(|action| action.to_string())
+            .collect()
+    }
+
+    /// Take an action for the current player, then fire their lasers
+    pub fn apply(&mut self, action: &str) -> Result<(), JsError> {
+        let action = StandardAction::from_str(action).map_err(to_js_error)?;
+        self.game.apply(action).map_err(to_js_error)
+    }
+
+    /// Take back the most recent action, returning it
+    pub fn undo(&mut self) -> Result<String, JsError> {
+        let action = self.game.undo().map_err(to_js_error)?;
+        Ok(action.to_string())
+    }
+
+    /// Squares of the pieces zapped after the most recent action
+    #[wasm_bindgen(js_name = lastZapped)]
+    pub fn last_zapped(&self) -> Vec<String> {
+        self.game
+            .last_zapped()
+            .iter()
+            .map(|(location, _)| location.to_string())
+            .collect()
+    }
+
+    /// The paths the lasers of the given player ("W" or "B") would take,
+    /// one per monarch in FEN order, each written as the squares it
+    /// crosses separated by spaces, starting from the monarch
+    #[wasm_bindgen(js_name = laserPaths)]
+    pub fn laser_paths(&self, player: &str) -> Result<Vec<String>, JsError> {
+        let color = Color::from_str(player).map_err(to_js_error)?;
+        let board = self.game.board();
+        Ok(laser::lasers(board, color)
+            .iter()
+            .map(|laser| {
+                let squares: Vec<String> = laser
+                    .path_on(board)
+                    .squares
+                    .iter()
+                    .map(|square| square.to_string())
+                    .collect();
+                squares.join(" ")
+            })
+            .collect())
+    }
+
+    /// "in_progress", "W" or "B" for the winner, or "draw"
+    pub fn result(&self) -> String {
+        match self.game.result(&ResultConfig::default()) {
+            GameResult::InProgress => "in_progress".to_string(),
+            GameResult::Win { winner, .. } => winner.to_string(),
+            GameResult::Draw { .. } => "draw".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod wasm_tests {
+    use super::*;
+    use crate::constants::STARTING_POSITION;
+
+    // JsError can only be created on wasm targets, so only
+    // the successful paths are exercised natively
+
+    #[test]
+    pub fn plays_through_bindings() {
+        assert_eq!(
+            normalize_fen("nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS").ok(),
+            Some(STARTING_POSITION.to_string())
+        );
+
+        let mut game = WasmGame::new(STARTING_POSITION).ok().unwrap();
+        assert_eq!(game.legal_actions().len(), 64);
+        assert!(game.apply("a7b6").is_ok());
+        assert_eq!(game.current_player(), "B");
+        assert_eq!(game.undo().ok(), Some("a7b6".to_string()));
+        assert_eq!(game.result(), "in_progress");
+    }
+
+    #[test]
+    pub fn reports_lasers_and_zaps() {
+        let mut game = WasmGame::new("nn7/8/8/8/8/8/8/SS7 W").ok().unwrap();
+        assert_eq!(game.laser_paths("B").ok().unwrap(), vec!["a1".to_string()]);
+        assert!(game.apply("a8U").is_ok());
+        assert_eq!(game.last_zapped(), vec!["a1".to_string()]);
+        assert_eq!(game.result(), "W");
+    }
+}
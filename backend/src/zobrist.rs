@@ -0,0 +1,117 @@
+use crate::board::*;
+use crate::constants::*;
+
+/// Enough squares for the largest board, indexed as y * MAX_BOARD_SIZE + x
+const SQUARES: usize = MAX_BOARD_SIZE * MAX_BOARD_SIZE;
+
+/// Every combination of color, kind and orientation
+const PIECES: usize = 16;
+
+/// Step of the splitmix64 generator, used so that the keys
+/// are the same on every run and every machine
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> ([[u64; SQUARES]; PIECES], u64) {
+    let mut keys = [[0; SQUARES]; PIECES];
+    let mut state = 0x4C45_4953_4552_4348;
+    let mut piece = 0;
+    while piece < PIECES {
+        let mut square = 0;
+        while square < SQUARES {
+            let (next, key) = splitmix64(state);
+            keys[piece][square] = key;
+            state = next;
+            square += 1;
+        }
+        piece += 1;
+    }
+    let (_, black_to_move) = splitmix64(state);
+    (keys, black_to_move)
+}
+
+const KEYS: ([[u64; SQUARES]; PIECES], u64) = generate_keys();
+
+/// Key xored into a position's hash when black is to move
+pub const BLACK_TO_MOVE: u64 = KEYS.1;
+
+fn piece_index(piece: &StandardPiece) -> usize {
+    let color = match piece.color {
+        Color::White => 0,
+        Color::Black => 8,
+    };
+    let kind = match piece.kind {
+        Kind::Monarch => 0,
+        Kind::Pawn => 4,
+    };
+    color + kind + piece.direction.quarter_turns()
+}
+
+/// Key for the given piece standing on the given location
+pub fn piece_key(piece: &StandardPiece, location: &GridLocation) -> u64 {
+    KEYS.0[piece_index(piece)][location.y * MAX_BOARD_SIZE + location.x]
+}
+
+/// Key for the player to move, zero for white
+pub fn player_key(player: Color) -> u64 {
+    match player {
+        Color::White => 0,
+        Color::Black => BLACK_TO_MOVE,
+    }
+}
+
+#[cfg(test)]
+pub mod zobrist_tests {
+    use super::*;
+    use crate::action::generation::generate_actions;
+    use crate::action::OptimizedAction;
+    use crate::rules::RuleSet;
+
+    fn from_scratch(grid: &LeiserChessGrid) -> u64 {
+        grid.pieces().fold(0, |hash, (location, piece)| {
+            hash ^ piece_key(&piece, &location)
+        })
+    }
+
+    #[test]
+    pub fn keys_are_distinct() {
+        let mut keys: Vec<u64> = KEYS.0.iter().flatten().copied().collect();
+        keys.push(BLACK_TO_MOVE);
+        let count = keys.len();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+    }
+
+    #[test]
+    pub fn hash_updates_incrementally() {
+        let fen = "nn6nn/se1sw2ne2/2NWsw4/3NE4/8/4SSse2/NENW3NW2/7SS";
+        let grid = LeiserChessGrid::from_str(fen).unwrap();
+        assert_eq!(grid.zobrist(), from_scratch(&grid));
+
+        for color in [Color::White, Color::Black] {
+            // The relaxed rules also shove monarchs
+            for action in generate_actions(&grid, color, &RuleSet::RELAXED) {
+                let mut board = grid.clone();
+                let undo = action.apply_unchecked(&mut board);
+                assert_eq!(board.zobrist(), from_scratch(&board), "{:?}", action);
+                action.unapply_unchecked(&mut board, &undo);
+                assert_eq!(board.zobrist(), grid.zobrist());
+            }
+        }
+    }
+
+    #[test]
+    pub fn side_to_move_changes_hash() {
+        let fen = "nn6nn/sesw1sesw1sesw/8/8/8/8/NENW1NENW1NENW/SS6SS";
+        let white: Position<LeiserChessGrid> = Position::from_str(&format!("{} W", fen)).unwrap();
+        let black: Position<LeiserChessGrid> = Position::from_str(&format!("{} B", fen)).unwrap();
+        assert_ne!(white.zobrist(), black.zobrist());
+        assert_eq!(white.zobrist() ^ black.zobrist(), BLACK_TO_MOVE);
+    }
+}